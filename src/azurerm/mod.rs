@@ -4,6 +4,8 @@ use crate::rules::{ Property, Selector };
 use reqwest;
 use serde_json::Value;
 use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Id {
@@ -30,10 +32,26 @@ impl TryFrom<&str> for Id {
   }
 }
 
+impl Id {
+  // ARM identifiers are case-insensitive, so references found in properties may not
+  // match the casing of the resource they point at
+  pub fn is_same(&self, other: &Id) -> bool {
+    self.subscription_id.eq_ignore_ascii_case(&other.subscription_id) &&
+    self.resource_group.eq_ignore_ascii_case(&other.resource_group) &&
+    self.kind.eq_ignore_ascii_case(&other.kind) &&
+    self.name.eq_ignore_ascii_case(&other.name)
+  }
+
+  // Equal for two ids exactly when `is_same` holds, for looking resources up by id
+  pub fn key(&self) -> String {
+    format!("{}/{}/{}/{}", self.subscription_id, self.resource_group, self.kind, self.name).to_ascii_lowercase()
+  }
+}
+
 fn translate_kind(kind: &str) -> &str {
-  match kind {
-    "Microsoft.Web/serverFarms" => "app_service_plan",
-    "Microsoft.Web/sites" => "app_service",
+  match kind.to_ascii_lowercase().as_str() {
+    "microsoft.web/serverfarms" => "app_service_plan",
+    "microsoft.web/sites" => "app_service",
     _ => kind,
   }
 }
@@ -58,20 +76,68 @@ impl Resource {
     &self.id().resource_group
   }
 
+  pub fn subscription(&self) -> &str {
+    &self.id().subscription_id
+  }
+
+  // The full ARM resource id, as returned by the API
+  pub fn arm_id(&self) -> &str {
+    self.1["id"].as_str().unwrap_or_default()
+  }
+
   pub fn get_property(&self, property: &Property) -> Value {
     match property {
       Property::Name => self.name().into(),
       Property::Kind => self.kind().into(),
       Property::Group => self.group().into(),
-      Property::Custom(key) => self.1[key].clone()
+      Property::Custom(path) => select_path(&self.1, path),
     }
   }
 
   pub fn selector_applies(&self, selector: &Selector) -> bool {
-    (selector.cloud == "azure" || selector.cloud == "*") &&
-    (selector.group == self.group() || selector.group == "*") &&
-    (selector.kind == self.kind() || selector.kind == "*") &&
-    (selector.name == self.name() || selector.name == "*")
+    selector.applies_to("azure", self.group(), self.kind(), self.name())
+  }
+}
+
+// Walks a dotted property path (e.g. `properties.networkAcls.ipRules[*].value`). A
+// `[*]` segment fans out over every element of an array, so any path containing one
+// yields an array of the matched values. Objects (e.g. `tags[*]`) fan out over their values.
+pub(crate) fn select_path(value: &Value, path: &str) -> Value {
+  let (key, rest) = match path.find(['.', '[']) {
+    Some(i) => path.split_at(i),
+    None => return value[path].clone(),
+  };
+  let value = if key.is_empty() { value } else { &value[key] };
+
+  if let Some(rest) = rest.strip_prefix("[*]") {
+    let rest = rest.trim_start_matches('.');
+    let elements: Vec<&Value> = match value {
+      Value::Array(elements) => elements.iter().collect(),
+      Value::Object(entries) => entries.values().collect(),
+      _ => return Value::Null,
+    };
+
+    Value::Array(
+      elements.into_iter()
+        .map(|element| if rest.is_empty() { element.clone() } else { select_path(element, rest) })
+        .flat_map(|selected| match selected {
+          Value::Array(nested) if rest.contains("[*]") => nested,
+          Value::Null => vec![],
+          other => vec![other],
+        })
+        .collect()
+    )
+  } else if let Some(rest) = rest.strip_prefix('[') {
+    let (index, rest) = rest.split_at(rest.find(']').unwrap_or(rest.len()));
+    let rest = rest.trim_start_matches(']').trim_start_matches('.');
+    let element = match index.parse::<usize>() {
+      Ok(index) => &value[index],
+      Err(_) => return Value::Null,
+    };
+
+    if rest.is_empty() { element.clone() } else { select_path(element, rest) }
+  } else {
+    select_path(value, &rest[1..])
   }
 }
 
@@ -96,104 +162,231 @@ impl TryFrom<Value> for Resource {
 //     }
 // }
 
+#[derive(Debug, PartialEq)]
+pub enum ResourcesError {
+  Io(String),
+  Invalid(String),
+}
+
+impl fmt::Display for ResourcesError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(reason) => write!(f, "Failed to read resources: {}", reason),
+      Self::Invalid(reason) => write!(f, "Invalid resources: {}", reason),
+    }
+  }
+}
+
+// Resources saved locally, either as a list (e.g. from `az resource list`) or an ARM list
+// response with a `value` list. Entries that aren't ARM resources are skipped, as they are
+// when fetched from Azure.
+pub fn parse_resources(path: impl AsRef<Path>) -> Result<Vec<Resource>, ResourcesError> {
+  let contents = std::fs::read_to_string(path).map_err(|e| ResourcesError::Io(e.to_string()))?;
+  let json: Value = serde_json::from_str(&contents).map_err(|e| ResourcesError::Invalid(e.to_string()))?;
+
+  let values = match json {
+    Value::Array(values) => values,
+    Value::Object(mut object) => match object.remove("value") {
+      Some(Value::Array(values)) => values,
+      _ => return Err(ResourcesError::Invalid("Expected a `value` list".to_owned())),
+    },
+    _ => return Err(ResourcesError::Invalid("Expected a list of resources".to_owned())),
+  };
+
+  Ok(values.into_iter().filter_map(|r| Resource::try_from(r).ok()).collect())
+}
+
+const MANAGEMENT_URL: &str = "https://management.azure.com";
 
 pub struct Client {
   pub client: reqwest::blocking::Client,
   pub bearer_token: String,
+  pub management_url: String, // e.g. a local mock of ARM in tests
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ClientError {
+  Request(String),      // the request couldn't be sent, or its response couldn't be read
+  Status(u16, String),  // Azure responded with an error status
+  Response(String),     // the response didn't have the expected shape
+}
+
+impl fmt::Display for ClientError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Request(reason) => write!(f, "Azure request failed: {}", reason),
+      Self::Status(status, body) => write!(f, "Azure responded with {}: {}", status, body),
+      Self::Response(reason) => write!(f, "Unexpected response from Azure: {}", reason),
+    }
+  }
+}
+
+fn read_json(response: reqwest::Result<reqwest::blocking::Response>) -> Result<Value, ClientError> {
+  let response = response.map_err(|e| ClientError::Request(e.to_string()))?;
+  let status = response.status();
+  let body = response.text().map_err(|e| ClientError::Request(e.to_string()))?;
+
+  if !status.is_success() {
+    return Err(ClientError::Status(status.as_u16(), body));
+  }
+
+  // e.g. a 202 Accepted for a long-running update
+  if body.trim().is_empty() {
+    return Ok(Value::Null);
+  }
+
+  serde_json::from_str(&body).map_err(|e| ClientError::Response(e.to_string()))
+}
+
+// The `value` array of an ARM list response
+fn list_values(json: Value) -> Result<Vec<Value>, ClientError> {
+  match json {
+    Value::Object(mut object) => match object.remove("value") {
+      Some(Value::Array(values)) => Ok(values),
+      _ => Err(ClientError::Response("expected a `value` list".to_owned())),
+    },
+    _ => Err(ClientError::Response("expected an object".to_owned())),
+  }
 }
 
 impl Client {
-  pub fn new(tenant_id: &str, client_id: &str, client_secret: &str) -> Client {
+  pub fn new(tenant_id: &str, client_id: &str, client_secret: &str) -> Result<Client, ClientError> {
     let client = reqwest::blocking::Client::new();
-    let bearer_token = get_bearer_token(&client, tenant_id, client_id, client_secret);
+    let bearer_token = get_bearer_token(&client, tenant_id, client_id, client_secret)?;
 
-    Client {
+    Ok(Client {
       client: client,
       bearer_token: bearer_token,
+      management_url: MANAGEMENT_URL.to_owned(),
+    })
+  }
+
+  // A client for an already authenticated session, against any ARM-compatible endpoint
+  pub fn with_token(management_url: &str, bearer_token: &str) -> Client {
+    Client {
+      client: reqwest::blocking::Client::new(),
+      bearer_token: bearer_token.to_owned(),
+      management_url: management_url.trim_end_matches('/').to_owned(),
     }
   }
 
-  pub fn get_subscriptions(&self) -> Vec<String> {
-    let res = self
-      .client
-      .get("https://management.azure.com/subscriptions")
-      .query(&[("api-version", "2016-06-01")])
-      .bearer_auth(self.bearer_token.to_owned())
-      .send()
-      .unwrap();
+  fn get(&self, url: &str, api_version: &str) -> Result<Value, ClientError> {
+    log::debug!(url = url; "GET");
+
+    read_json(
+      self
+        .client
+        .get(url)
+        .query(&[("api-version", api_version)])
+        .bearer_auth(self.bearer_token.to_owned())
+        .send()
+    )
+  }
 
-    let json = res.json::<Value>().unwrap();
+  pub fn get_subscriptions(&self) -> Result<Vec<String>, ClientError> {
+    let json = self.get(&format!("{}/subscriptions", self.management_url), "2016-06-01")?;
 
-    json["value"]
-      .as_array()
-      .unwrap()
+    list_values(json)?
       .iter()
-      .map(|v| v["subscriptionId"].as_str().unwrap().to_owned())
-      .collect()
+      .map(|v| v["subscriptionId"].as_str().map(str::to_owned))
+      .collect::<Option<_>>()
+      .ok_or_else(|| ClientError::Response("subscription without a `subscriptionId`".to_owned()))
   }
 
-  pub fn get_resource_groups(&self, subscription_id: &str) -> Vec<String> {
+  pub fn get_resource_groups(&self, subscription_id: &str) -> Result<Vec<String>, ClientError> {
     let url = format!(
-      "https://management.azure.com/subscriptions/{}/resourcegroups",
-      subscription_id
+      "{}/subscriptions/{}/resourcegroups",
+      self.management_url, subscription_id
     );
-    let res = self
-      .client
-      .get(&url)
-      .query(&[("api-version", "2019-10-01")])
-      .bearer_auth(self.bearer_token.to_owned())
-      .send()
-      .unwrap();
-
-    let json = res.json::<Value>().unwrap();
-
-    json["value"]
-      .as_array()
-      .unwrap()
+    let json = self.get(&url, "2019-10-01")?;
+
+    list_values(json)?
       .iter()
-      .map(|v| v["name"].as_str().unwrap().to_owned())
-      .collect()
+      .map(|v| v["name"].as_str().map(str::to_owned))
+      .collect::<Option<_>>()
+      .ok_or_else(|| ClientError::Response("resource group without a `name`".to_owned()))
   }
 
-  pub fn get_resources(&self, subscription_id: &str, resource_group_name: &str) -> Vec<Resource> {
+  pub fn get_resources(&self, subscription_id: &str, resource_group_name: &str) -> Result<Vec<Resource>, ClientError> {
     let url = format!(
-      "https://management.azure.com/subscriptions/{}/resourceGroups/{}/resources",
-      subscription_id, resource_group_name
+      "{}/subscriptions/{}/resourceGroups/{}/resources",
+      self.management_url, subscription_id, resource_group_name
     );
-    let res = self
-      .client
-      .get(&url)
-      .query(&[("api-version", "2019-10-01")])
-      .bearer_auth(self.bearer_token.to_owned())
-      .send()
-      .unwrap();
-
-    let json = res.json::<Value>().unwrap();
-
-    json["value"].as_array()
-      .map(|arr| {
-        arr.iter()
-          .filter_map(|r| Resource::try_from(r.clone()).ok())
-          .collect()
-      })
-      .unwrap()
+    let json = self.get(&url, "2019-10-01")?;
+
+    Ok(
+      list_values(json)?
+        .into_iter()
+        .filter_map(|r| Resource::try_from(r).ok())
+        .collect()
+    )
+  }
+
+  // The newest stable API version of a resource's type (e.g. `Microsoft.Web/sites`), which
+  // ARM requires for operations on the resource itself
+  pub fn get_api_version(&self, resource_id: &str) -> Result<String, ClientError> {
+    let invalid = || ClientError::Response(format!("can't find the provider of {}", resource_id));
+    let (scope, provider) = split_provider(resource_id).ok_or_else(invalid)?;
+    let mut parts = provider.split('/');
+    let namespace = parts.next().ok_or_else(invalid)?;
+    let resource_type: Vec<&str> = parts.step_by(2).collect();
+    let resource_type = resource_type.join("/");
+
+    let subscription = scope.split('/').take(3).collect::<Vec<&str>>().join("/");
+    let json = self.get(&format!("{}{}/providers/{}", self.management_url, subscription, namespace), "2021-04-01")?;
+
+    json["resourceTypes"].as_array()
+      .and_then(|types| types.iter().find(|t| {
+        matches!(t["resourceType"].as_str(), Some(t) if t.eq_ignore_ascii_case(&resource_type))
+      }))
+      .and_then(|t| t["apiVersions"].as_array())
+      .and_then(|versions| versions.iter().filter_map(Value::as_str).find(|v| !v.ends_with("-preview")))
+      .map(str::to_owned)
+      .ok_or_else(|| ClientError::Response(format!("no API version for {}/{}", namespace, resource_type)))
+  }
+
+  // Merges `body` into the resource, e.g. `{"properties":{"httpsOnly":true}}`
+  pub fn patch_resource(&self, resource_id: &str, body: &Value) -> Result<Value, ClientError> {
+    let api_version = self.get_api_version(resource_id)?;
+    let url = format!("{}{}", self.management_url, resource_id);
+
+    log::debug!(url = url.as_str(), body:% = body; "PATCH");
+
+    read_json(
+      self
+        .client
+        .patch(&url)
+        .query(&[("api-version", api_version.as_str())])
+        .bearer_auth(self.bearer_token.to_owned())
+        .json(body)
+        .send()
+    )
   }
 }
 
+// Splits `/subscriptions/.../resourceGroups/.../providers/Microsoft.Web/sites/app` into its
+// scope and provider part (`Microsoft.Web/sites/app`)
+fn split_provider(resource_id: &str) -> Option<(&str, &str)> {
+  let start = resource_id.to_ascii_lowercase().find("/providers/")?;
+
+  Some((&resource_id[..start], &resource_id[start + "/providers/".len()..]))
+}
+
 fn get_bearer_token(
   client: &reqwest::blocking::Client,
   tenant_id: &str,
   client_id: &str,
   client_secret: &str,
-) -> String {
+) -> Result<String, ClientError> {
   let token_endpoint = format!("https://login.windows.net/{}/oauth2/token", tenant_id);
   let body = format!("grant_type=client_credentials&client_id={}&resource=https%3A%2F%2Fmanagement.core.windows.net%2F&client_secret={}",
         client_id,
         client_secret
     );
 
-  let res = client.post(&token_endpoint).body(body).send().unwrap();
+  let json = read_json(client.post(&token_endpoint).body(body).send())?;
 
-  let json: Value = res.json().unwrap();
-  json["access_token"].as_str().unwrap().to_owned()
+  json["access_token"].as_str()
+    .map(str::to_owned)
+    .ok_or_else(|| ClientError::Response("no `access_token` in the token response".to_owned()))
 }
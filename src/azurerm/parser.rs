@@ -1,7 +1,7 @@
 use nom::{
   bytes::complete::take_until,
   bytes::complete::take_while1,
-  bytes::complete::tag,
+  bytes::complete::tag_no_case,
   combinator::all_consuming,
   sequence::tuple,
   IResult
@@ -26,13 +26,14 @@ fn kind(i: &str) -> ParseResult<&str> {
 }
 
 fn name(i: &str) -> ParseResult<&str> {
-    take_while1(|c| char::is_alphanumeric(c) || c == '-' || c == '-' || c == ' ')(i)
+    take_while1(|c| char::is_alphanumeric(c) || c == '-' || c == '_' || c == '.' || c == ' ')(i)
 }
 
 pub fn parse_id(i: &str) -> Option<(&str, &str, &str, &str, &str)> {
     // /subscriptions/00d88f1a-26e6-4665-9eee-00359b7f1717/resourceGroups/test-group/providers/Microsoft.Storage/storageAccounts/ihbtesting123
-    let parser = tuple((tag("/subscriptions/"), subscription_id, tag("/resourceGroups/"), resource_group, tag("/providers/"), provider, tag("/"), kind, tag("/"), name));
-    let (_, (_, subscription_id, _, resource_group, _, provider, _, kind, _, name)) = all_consuming(parser)(i).unwrap();
+    // ARM is not consistent about the casing of the fixed segments (e.g. `resourcegroups`)
+    let parser = tuple((tag_no_case("/subscriptions/"), subscription_id, tag_no_case("/resourceGroups/"), resource_group, tag_no_case("/providers/"), provider, tag_no_case("/"), kind, tag_no_case("/"), name));
+    let (_, (_, subscription_id, _, resource_group, _, provider, _, kind, _, name)) = all_consuming(parser)(i).ok()?;
 
     Some((subscription_id, resource_group, provider, kind, name))
 }
@@ -0,0 +1,145 @@
+use crate::engine::{ Outcome, ResourceCompliance };
+use serde_json::{ json, Value };
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::Path;
+
+// {
+//   "violations": [
+//     { "rule": "app-location", "resource": "/subscriptions/.../providers/Microsoft.Web/sites/legacy-app" }
+//   ]
+// }
+//
+// Violations are matched by rule id and ARM resource id (ignoring case). Rules without an
+// `@id` are identified by their selector and condition, so give a rule an `@id` for its
+// baselined violations to survive changes to its condition.
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Baseline {
+  violations: BTreeSet<(String, String)>,
+}
+
+impl Baseline {
+  // Every violation in `results`, including those already baselined but excluding waived ones
+  pub fn from_results(results: &[ResourceCompliance]) -> Self {
+    let violations = results.iter()
+      .flat_map(|resource| {
+        resource.evaluations.iter()
+          .filter(|evaluation| matches!(evaluation.outcome, Outcome::NonCompliant { .. } | Outcome::Baselined { .. }))
+          .map(move |evaluation| (evaluation.rule.id(), resource.resource_id.to_ascii_lowercase()))
+      })
+      .collect();
+
+    Baseline { violations }
+  }
+
+  pub fn contains(&self, rule_id: &str, resource_id: &str) -> bool {
+    self.violations.contains(&(rule_id.to_owned(), resource_id.to_ascii_lowercase()))
+  }
+
+  pub fn len(&self) -> usize {
+    self.violations.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.violations.is_empty()
+  }
+
+  pub fn to_json(&self) -> Value {
+    let violations: Vec<Value> = self.violations.iter()
+      .map(|(rule, resource)| json!({ "rule": rule, "resource": resource }))
+      .collect();
+
+    json!({ "violations": violations })
+  }
+}
+
+impl TryFrom<&Value> for Baseline {
+  type Error = BaselineError;
+
+  fn try_from(value: &Value) -> Result<Self, Self::Error> {
+    let violations = value["violations"].as_array()
+      .ok_or_else(|| BaselineError::Invalid("Expected a list of `violations`".to_owned()))?;
+
+    let violations = violations.iter()
+      .map(|violation| match (violation["rule"].as_str(), violation["resource"].as_str()) {
+        (Some(rule), Some(resource)) => Ok((rule.to_owned(), resource.to_ascii_lowercase())),
+        _ => Err(BaselineError::Invalid("Violation is missing `rule` or `resource`".to_owned())),
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(Baseline { violations })
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BaselineError {
+  Io(String),
+  Invalid(String),
+}
+
+impl fmt::Display for BaselineError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(reason) => write!(f, "Failed to read or write baseline: {}", reason),
+      Self::Invalid(reason) => write!(f, "Invalid baseline: {}", reason),
+    }
+  }
+}
+
+pub fn parse_baseline(path: impl AsRef<Path>) -> Result<Baseline, BaselineError> {
+  let contents = std::fs::read_to_string(path).map_err(|e| BaselineError::Io(e.to_string()))?;
+  let json: Value = serde_json::from_str(&contents).map_err(|e| BaselineError::Invalid(e.to_string()))?;
+
+  Baseline::try_from(&json)
+}
+
+pub fn write_baseline(path: impl AsRef<Path>, baseline: &Baseline) -> Result<(), BaselineError> {
+  let contents = serde_json::to_string_pretty(&baseline.to_json()).map_err(|e| BaselineError::Invalid(e.to_string()))?;
+
+  std::fs::write(path, contents + "\n").map_err(|e| BaselineError::Io(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use chrono::{ TimeZone, Utc };
+
+  #[test]
+  fn test_baseline() {
+    let resources: Vec<Resource> = vec![
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/old-app", "location": "westeurope" }),
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/new-app", "location": "westeurope" }),
+    ].into_iter().map(|r| Resource::try_from(r).unwrap()).collect();
+    let rules = parse_source("azure.* {\n  @id(\"app-location\")\n  location = \"uksouth\"\n}\n").unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    let baseline = Baseline::try_from(&json!({
+      "violations": [
+        { "rule": "app-location", "resource": "/subscriptions/sub-a/resourcegroups/web-rg/providers/Microsoft.Web/sites/OLD-APP" },
+      ]
+    })).unwrap();
+    assert!(baseline.contains("app-location", resources[0].arm_id()));
+
+    let results = Engine::new().at(now).with_baseline(baseline).evaluate(&resources, &rules);
+    let outcomes: Vec<&str> = results.iter().map(|r| r.evaluations[0].outcome.label()).collect();
+    assert_eq!(outcomes, vec!["baselined", "noncompliant"]);
+
+    // Rewriting the baseline keeps known violations and adds new ones
+    let rewritten = Baseline::from_results(&results);
+    assert_eq!(rewritten.len(), 2);
+    assert_eq!(Baseline::try_from(&rewritten.to_json()), Ok(rewritten));
+  }
+
+  #[test]
+  fn test_invalid_baseline() {
+    assert_eq!(
+      Baseline::try_from(&json!({ "violations": [{ "rule": "app-location" }] })),
+      Err(BaselineError::Invalid("Violation is missing `rule` or `resource`".to_owned()))
+    );
+  }
+}
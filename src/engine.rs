@@ -0,0 +1,408 @@
+use crate::azurerm::Resource;
+use crate::baseline::Baseline;
+use crate::index::{ ResourceIndex, RuleIndex };
+use crate::rules::{ type_name, Expression, Property, Rule, Severity };
+use crate::waivers::Waiver;
+use chrono::{ DateTime, Utc };
+use rayon::prelude::*;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+  Compliant,
+  NonCompliant { actual: Value },
+  Waived { actual: Value, waiver: Waiver }, // non-compliant, but covered by a current waiver
+  Baselined { actual: Value },              // non-compliant, but already present in the baseline
+  NotApplicable,                            // the rule's selector doesn't match the resource
+  Error { reason: String },                 // the rule couldn't be evaluated (e.g. a missing property)
+}
+
+impl Outcome {
+  pub fn label(&self) -> &'static str {
+    match self {
+      Outcome::Compliant => "compliant",
+      Outcome::NonCompliant { .. } => "noncompliant",
+      Outcome::Waived { .. } => "waived",
+      Outcome::Baselined { .. } => "baselined",
+      Outcome::NotApplicable => "not_applicable",
+      Outcome::Error { .. } => "error",
+    }
+  }
+}
+
+pub struct Evaluation {
+  pub rule: Rule,
+  pub outcome: Outcome,
+  pub actual: Option<Value>, // the observed value; `None` when missing or the rule didn't apply
+}
+
+impl Evaluation {
+  pub fn found(&self) -> String {
+    match &self.actual {
+      Some(value) => value.to_string(),
+      None => "missing".to_owned(),
+    }
+  }
+}
+
+pub struct ResourceCompliance {
+  pub resource_id: String,
+  pub subscription_id: String,
+  pub resource_group: String,
+  pub resource_name: String,
+  pub resource_type: String,
+
+  pub evaluations: Vec<Evaluation>,
+  pub expired_waivers: Vec<(Rule, Waiver)>, // non-compliant, and the waiver covering it has expired
+}
+
+impl ResourceCompliance {
+  pub fn count(&self, matches: fn(&Outcome) -> bool) -> usize {
+    self.evaluations.iter().filter(|evaluation| matches(&evaluation.outcome)).count()
+  }
+}
+
+// `resources` indexes everything fetched alongside `resource`, used to follow references
+fn evaluate_expression(resource: &Resource, resources: &ResourceIndex, expression: &Expression) -> Value {
+  match expression {
+    Expression::Property(property) => resource.get_property(property),
+    Expression::Quantified(_, inner) => evaluate_expression(resource, resources, inner),
+    Expression::Call(function, inner) => function.apply(&evaluate_expression(resource, resources, inner)),
+    Expression::Reference(inner, property) => match evaluate_expression(resource, resources, inner) {
+      Value::Array(ids) => Value::Array(
+        ids.iter()
+          .filter_map(|id| resources.get(id))
+          .map(|r| r.get_property(property))
+          .collect()
+      ),
+      id => resources.get(&id).map_or(Value::Null, |r| r.get_property(property)),
+    },
+    Expression::ReferencedBy(kind, property) => Value::Array(
+      resources.referenced_by(resource, kind, property).iter()
+        .map(|r| r.get_property(&Property::Custom("id".to_owned())))
+        .collect()
+    ),
+  }
+}
+
+// Returns the outcome along with the observed value (`None` if missing)
+fn evaluate_rule(resource: &Resource, resources: &ResourceIndex, rule: &Rule, now: DateTime<Utc>) -> (Outcome, Option<Value>) {
+  let value = evaluate_expression(resource, resources, &rule.expression);
+
+  if value.is_null() {
+    return (Outcome::Error { reason: format!("{} is missing or null", rule.expression) }, None);
+  }
+
+  let condition = match rule.condition.resolve(&|property| resource.get_property(property), now) {
+    Some(condition) => condition,
+    None => {
+      let reason = "a property referenced by the condition has no value".to_owned();
+
+      return (Outcome::Error { reason }, Some(value));
+    }
+  };
+
+  let (values, is_compliant) = match (&rule.expression, value) {
+    (Expression::Quantified(quantifier, _), value) => {
+      let values = match value {
+        Value::Array(values) => values,
+        value => vec![value],
+      };
+      let is_compliant = quantifier.is_compliant(&values, &condition);
+
+      (values, is_compliant)
+    }
+    (_, value) => {
+      let is_compliant = condition.is_compliant(&value);
+
+      (vec![value], is_compliant)
+    }
+  };
+
+  let mismatch = values.iter().find(|value| !condition.accepts(value)).map(|value| match type_name(value) {
+    found if found == condition.value_type() => format!("expected {} to be a {} but found {}", rule.expression, condition.value_kind(), value),
+    found => format!("expected {} to be a {} but found {}", rule.expression, condition.value_type(), found),
+  });
+  let actual = match &rule.expression {
+    Expression::Quantified(..) => Value::Array(values),
+    _ => values.into_iter().next().unwrap_or(Value::Null),
+  };
+
+  if let Some(reason) = mismatch {
+    return (Outcome::Error { reason }, Some(actual));
+  }
+
+  if is_compliant {
+    (Outcome::Compliant, Some(actual))
+  } else {
+    (Outcome::NonCompliant { actual: actual.clone() }, Some(actual))
+  }
+}
+
+fn evaluate_rules(
+  resource: &Resource,
+  resources: &ResourceIndex,
+  rules: &[&Rule], // the rules whose selectors apply to `resource`
+  not_applicable: &[&Rule], // the rules whose selectors don't, recorded without being evaluated
+  waivers: &[Waiver],
+  baseline: &Baseline,
+  now: DateTime<Utc>, // the evaluation time, for date conditions and waiver expiry
+) -> ResourceCompliance {
+  let resource_name = resource.name();
+  let resource_kind = resource.kind();
+
+  let mut evaluations: Vec<Evaluation> = Vec::new();
+  let mut expired_waivers: Vec<(Rule, Waiver)> = Vec::new();
+
+  log::trace!(resource = resource.arm_id(), candidates = rules.len(); "matched rules");
+
+  for &rule in rules {
+    let actual = match evaluate_rule(resource, resources, rule, now) {
+      (Outcome::NonCompliant { actual }, _) => actual,
+      (outcome, actual) => {
+        log::debug!(rule = rule.id().as_str(), resource = resource.arm_id(), outcome = outcome.label(); "evaluated rule");
+        evaluations.push(Evaluation { rule: rule.clone(), outcome, actual });
+        continue;
+      }
+    };
+
+    // Prefer a current waiver over an expired one when several cover the same failure
+    let mut applicable = waivers.iter().filter(|w| w.applies(rule, resource));
+    let current = applicable.clone().find(|w| !w.is_expired(now.date_naive()));
+
+    let outcome = match (current, applicable.next()) {
+      (Some(waiver), _) => Outcome::Waived { actual: actual.clone(), waiver: waiver.clone() },
+      (None, Some(expired)) => {
+        expired_waivers.push((rule.clone(), expired.clone()));
+        Outcome::NonCompliant { actual: actual.clone() }
+      }
+      (None, None) if baseline.contains(&rule.id(), resource.arm_id()) => Outcome::Baselined { actual: actual.clone() },
+      (None, None) => Outcome::NonCompliant { actual: actual.clone() },
+    };
+
+    log::debug!(rule = rule.id().as_str(), resource = resource.arm_id(), outcome = outcome.label(); "evaluated rule");
+
+    evaluations.push(Evaluation { rule: rule.clone(), outcome, actual: Some(actual) });
+  }
+
+  for &rule in not_applicable {
+    evaluations.push(Evaluation { rule: rule.clone(), outcome: Outcome::NotApplicable, actual: None });
+  }
+
+  ResourceCompliance {
+    resource_id: resource.arm_id().to_owned(),
+    subscription_id: resource.subscription().to_owned(),
+    resource_group: resource.group().to_owned(),
+    resource_name: resource_name.to_owned(),
+    resource_type: resource_kind.to_owned(),
+    evaluations,
+    expired_waivers,
+  }
+}
+
+// Evaluates rules against resources, applying waivers as of a point in time
+//
+//   let results = Engine::new().with_waivers(waivers).evaluate(&resources, &rules);
+pub struct Engine {
+  waivers: Vec<Waiver>,
+  baseline: Baseline,
+  now: DateTime<Utc>,
+}
+
+impl Engine {
+  pub fn new() -> Self {
+    Engine { waivers: Vec::new(), baseline: Baseline::default(), now: Utc::now() }
+  }
+
+  pub fn with_waivers(mut self, waivers: Vec<Waiver>) -> Self {
+    self.waivers = waivers;
+    self
+  }
+
+  // Violations already in `baseline` are reported as `Outcome::Baselined` rather than non-compliant
+  pub fn with_baseline(mut self, baseline: Baseline) -> Self {
+    self.baseline = baseline;
+    self
+  }
+
+  // Evaluate as of `now` rather than the current time, for date conditions and waiver expiry
+  pub fn at(mut self, now: DateTime<Utc>) -> Self {
+    self.now = now;
+    self
+  }
+
+  // `resources` should include anything referenced by `ref(...)` or `referenced_by(...)` rules.
+  // Resources are evaluated in parallel; results keep the order of `resources`.
+  pub fn evaluate(&self, resources: &[Resource], rules: &[Rule]) -> Vec<ResourceCompliance> {
+    let index = RuleIndex::new(rules);
+    let resource_index = ResourceIndex::new(resources, rules);
+
+    resources
+      .par_iter()
+      .map(|r| {
+        let (candidates, not_applicable) = index.partition(r);
+
+        evaluate_rules(r, &resource_index, &candidates, &not_applicable, &self.waivers, &self.baseline, self.now)
+      })
+      .collect()
+  }
+}
+
+impl Default for Engine {
+  fn default() -> Self {
+    Engine::new()
+  }
+}
+
+#[derive(Default)]
+pub struct ResourceGroupCompliance {
+  pub resource_count: usize,         // the total number of resources evaluated
+  pub compliant_resources: usize,    // the number of resources with no violations or errors
+  pub noncompliant_resources: usize, // the number of resources non-compliant with at least one rule (new or baselined)
+  pub errored_resources: usize,      // the number of resources with errors but no violations
+
+  pub evaluated_rules: usize, // the total number of applicable rule evaluations (e.g. 1 rule * 3 resources = 3 evaluations)
+  pub compliant_rule_evaluations: usize, // the total number of rules that evaluated as compliant
+  pub noncompliant_rule_evaluations: usize, // the total number of rules that evaluated as noncompliant
+  pub waived_rule_evaluations: usize, // the total number of noncompliant evaluations covered by a waiver
+  pub baselined_rule_evaluations: usize, // the total number of noncompliant evaluations already in the baseline
+  pub error_rule_evaluations: usize, // the total number of rules that could not be evaluated
+  pub nonapplicable_rule_evaluations: usize, // the total number of rules whose selector didn't match
+
+  pub highest_severity: Option<Severity>, // the severity of the most severe non-compliant (new) evaluation
+}
+
+impl ResourceGroupCompliance {
+  pub fn from_results(results: &[ResourceCompliance]) -> Self {
+    results.iter().fold(ResourceGroupCompliance::default(), accumulate_group_compliance)
+  }
+}
+
+fn accumulate_group_compliance(
+  group_compliance: ResourceGroupCompliance,
+  resource_compliance: &ResourceCompliance,
+) -> ResourceGroupCompliance {
+  let compliant = resource_compliance.count(|o| matches!(o, Outcome::Compliant));
+  let noncompliant = resource_compliance.count(|o| matches!(o, Outcome::NonCompliant { .. }));
+  let waived = resource_compliance.count(|o| matches!(o, Outcome::Waived { .. }));
+  let baselined = resource_compliance.count(|o| matches!(o, Outcome::Baselined { .. }));
+  let errors = resource_compliance.count(|o| matches!(o, Outcome::Error { .. }));
+  let nonapplicable = resource_compliance.count(|o| matches!(o, Outcome::NotApplicable));
+  let violations = noncompliant + baselined;
+  let highest_severity = resource_compliance.evaluations.iter()
+    .filter(|evaluation| matches!(evaluation.outcome, Outcome::NonCompliant { .. }))
+    .map(|evaluation| evaluation.rule.metadata.severity)
+    .max();
+
+  ResourceGroupCompliance {
+    resource_count: group_compliance.resource_count + 1,
+    compliant_resources: group_compliance.compliant_resources
+      + (if violations == 0 && errors == 0 { 1 } else { 0 }),
+    noncompliant_resources: group_compliance.noncompliant_resources + (if violations > 0 { 1 } else { 0 }),
+    errored_resources: group_compliance.errored_resources
+      + (if violations == 0 && errors > 0 { 1 } else { 0 }),
+
+    evaluated_rules: group_compliance.evaluated_rules + compliant + noncompliant + waived + baselined + errors,
+    compliant_rule_evaluations: group_compliance.compliant_rule_evaluations + compliant,
+    noncompliant_rule_evaluations: group_compliance.noncompliant_rule_evaluations + noncompliant,
+    waived_rule_evaluations: group_compliance.waived_rule_evaluations + waived,
+    baselined_rule_evaluations: group_compliance.baselined_rule_evaluations + baselined,
+    error_rule_evaluations: group_compliance.error_rule_evaluations + errors,
+    nonapplicable_rule_evaluations: group_compliance.nonapplicable_rule_evaluations + nonapplicable,
+
+    highest_severity: group_compliance.highest_severity.max(highest_severity),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser;
+  use chrono::TimeZone;
+  use serde_json::json;
+  use std::convert::TryFrom;
+
+  fn evaluate(source: &str, resource: Value) -> ResourceCompliance {
+    let resource = Resource::try_from(resource).unwrap();
+    let rules = parser::parse_source(source).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    Engine::new().at(now).evaluate(std::slice::from_ref(&resource), &rules).remove(0)
+  }
+
+  #[test]
+  fn test_evaluate_rules_outcomes() {
+    let resource = json!({
+      "id": "/subscriptions/00d88f1a/resourceGroups/test-rg/providers/Microsoft.Web/sites/test-app",
+      "location": "westeurope",
+      "properties": { "httpsOnly": true },
+    });
+    let source = "azure.test-rg {\n  location = \"westeurope\"\n  location = \"uksouth\"\n  locaton = \"uksouth\"\n  properties = \"x\"\n}\nazure.other-rg {\n  location = \"uksouth\"\n}\n";
+
+    let compliance = evaluate(source, resource);
+    let evaluations = &compliance.evaluations;
+    let outcomes: Vec<Outcome> = evaluations.iter().map(|e| e.outcome.clone()).collect();
+
+    assert_eq!(outcomes, vec![
+      Outcome::Compliant,
+      Outcome::NonCompliant { actual: json!("westeurope") },
+      Outcome::Error { reason: "locaton is missing or null".to_owned() },
+      Outcome::Error { reason: "expected properties to be a string but found object".to_owned() },
+      Outcome::NotApplicable,
+    ]);
+    assert_eq!(ResourceGroupCompliance::from_results(std::slice::from_ref(&compliance)).nonapplicable_rule_evaluations, 1);
+
+    let found: Vec<String> = evaluations.iter().map(Evaluation::found).collect();
+    assert_eq!(found, vec!["\"westeurope\"", "\"westeurope\"", "missing", "{\"httpsOnly\":true}", "missing"]);
+    assert_eq!(
+      format!("expected {}, found {}", evaluations[1].rule, evaluations[1].found()),
+      "expected location = \"uksouth\", found \"westeurope\""
+    );
+  }
+
+  #[test]
+  fn test_negated_unparseable_value() {
+    let resource = json!({
+      "id": "/subscriptions/00d88f1a/resourceGroups/test-rg/providers/Microsoft.KeyVault/vaults/test-kv",
+      "properties": { "expiry": "soon", "minTlsVersion": "latest" },
+    });
+    let source = "azure.* {\n  properties.expiry not within 30d\n  version(properties.minTlsVersion) not < \"1.2\"\n}\n";
+
+    let outcomes: Vec<Outcome> = evaluate(source, resource).evaluations.into_iter().map(|e| e.outcome).collect();
+
+    assert_eq!(outcomes, vec![
+      Outcome::Error { reason: "expected properties.expiry to be a date but found \"soon\"".to_owned() },
+      Outcome::Error { reason: "expected version(properties.minTlsVersion) to be a version but found \"latest\"".to_owned() },
+    ]);
+  }
+
+  #[test]
+  fn test_element_conditions() {
+    let nsg = |rules: Value| json!({
+      "id": "/subscriptions/00d88f1a/resourceGroups/test-rg/providers/Microsoft.Network/networkSecurityGroups/test-nsg",
+      "properties": { "securityRules": rules },
+    });
+    let rule = |source: &str, port: &str| json!({ "properties": { "sourceAddressPrefix": source, "destinationPortRange": port } });
+    let source = "azure.* {\n  none(properties.securityRules[*]) { properties.sourceAddressPrefix = \"*\" and properties.destinationPortRange = \"22\" }\n}\n";
+    let outcome = |resource: Value| evaluate(source, resource).evaluations.remove(0).outcome;
+
+    assert_eq!(outcome(nsg(json!([rule("10.0.0.0/8", "22"), rule("*", "443")]))), Outcome::Compliant);
+    assert!(matches!(outcome(nsg(json!([rule("10.0.0.0/8", "22"), rule("*", "22")]))), Outcome::NonCompliant { .. }));
+    assert_eq!(outcome(nsg(json!([]))), Outcome::Compliant);
+  }
+
+  #[test]
+  fn test_public_sources_with_service_tags() {
+    let nsg = |sources: &[&str]| json!({
+      "id": "/subscriptions/00d88f1a/resourceGroups/test-rg/providers/Microsoft.Network/networkSecurityGroups/test-nsg",
+      "properties": {
+        "securityRules": sources.iter().map(|s| json!({ "properties": { "sourceAddressPrefix": s } })).collect::<Vec<_>>(),
+      },
+    });
+    let source = "azure.* {\n  any(properties.securityRules[*]) { is_public_ip(properties.sourceAddressPrefix) = true }\n}\n";
+    let outcome = |resource: Value| evaluate(source, resource).evaluations.remove(0).outcome;
+
+    assert!(matches!(outcome(nsg(&["VirtualNetwork", "AzureLoadBalancer", "10.0.0.0/8"])), Outcome::NonCompliant { .. }));
+    assert_eq!(outcome(nsg(&["VirtualNetwork", "AzureLoadBalancer", "*"])), Outcome::Compliant);
+  }
+}
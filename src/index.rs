@@ -0,0 +1,192 @@
+use crate::azurerm::{ Id, Resource };
+use crate::rules::{ Expression, Property, Rule };
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// Rules bucketed by their selector's cloud, kind, group and name, where any part may be
+// the `*` wildcard bucket. A resource only visits the buckets matching its own parts (or
+// `*`), so rules whose selectors can't apply are never evaluated.
+pub struct RuleIndex<'a> {
+  rules: &'a [Rule],
+  buckets: HashMap<[&'a str; 4], Vec<usize>>, // indexes into `rules`, in file order
+}
+
+impl<'a> RuleIndex<'a> {
+  pub fn new(rules: &'a [Rule]) -> Self {
+    let mut buckets: HashMap<[&str; 4], Vec<usize>> = HashMap::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+      let selector = &rule.selector;
+
+      buckets.entry([&selector.cloud, &selector.kind, &selector.group, &selector.name]).or_default().push(i);
+    }
+
+    RuleIndex { rules, buckets }
+  }
+
+  // The rules whose selectors apply to `resource` and the rules whose selectors don't, each
+  // in the order they were given
+  pub fn partition(&self, resource: &Resource) -> (Vec<&'a Rule>, Vec<&'a Rule>) {
+    let matches = self.matches(resource);
+    let (candidates, others): (Vec<_>, Vec<_>) = self.rules.iter()
+      .enumerate()
+      .partition(|(i, _)| matches.binary_search(i).is_ok());
+
+    (candidates.into_iter().map(|(_, rule)| rule).collect(), others.into_iter().map(|(_, rule)| rule).collect())
+  }
+
+  // Indexes into `rules`, sorted
+  fn matches(&self, resource: &Resource) -> Vec<usize> {
+    let parts = ["azure", resource.kind(), resource.group(), resource.name()];
+    let mut matches: Vec<usize> = Vec::new();
+
+    // Every combination of each part either matching exactly or by wildcard
+    for mask in 0..(1 << parts.len()) {
+      let mut key = parts;
+
+      for (i, part) in key.iter_mut().enumerate() {
+        if mask & (1 << i) != 0 {
+          *part = "*";
+        }
+      }
+
+      if let Some(bucket) = self.buckets.get(&key) {
+        matches.extend(bucket);
+      }
+    }
+
+    matches.sort_unstable();
+    matches.dedup();
+    matches
+  }
+}
+
+// Resources keyed by their (lowercased) ARM id, so references can be followed without
+// scanning every resource. Reverse references are only indexed for the
+// `referenced_by(kind, property)` pairs the rules actually use.
+pub struct ResourceIndex<'a> {
+  resources: HashMap<String, &'a Resource>,
+  referrers: HashMap<(String, Property), HashMap<String, Vec<&'a Resource>>>, // referenced id to referrers, in input order
+}
+
+impl<'a> ResourceIndex<'a> {
+  pub fn new(resources: &'a [Resource], rules: &'a [Rule]) -> Self {
+    let mut referrers: HashMap<(String, Property), HashMap<String, Vec<&Resource>>> = HashMap::new();
+
+    for rule in rules {
+      collect_referenced_by(&rule.expression, &mut |kind, property| {
+        referrers.entry((kind.to_owned(), property.clone())).or_insert_with(|| {
+          let mut targets: HashMap<String, Vec<&Resource>> = HashMap::new();
+
+          for r in resources.iter().filter(|r| r.kind().eq_ignore_ascii_case(kind)) {
+            let mut keys = Vec::new();
+
+            collect_keys(&r.get_property(property), &mut keys);
+            keys.sort_unstable();
+            keys.dedup();
+
+            for key in keys {
+              targets.entry(key).or_default().push(r);
+            }
+          }
+
+          targets
+        });
+      });
+    }
+
+    ResourceIndex { resources: resources.iter().map(|r| (r.id().key(), r)).collect(), referrers }
+  }
+
+  // The resource a property value refers to, if it's an ARM id of one of the resources
+  pub fn get(&self, id: &Value) -> Option<&'a Resource> {
+    let id = Id::try_from(id.as_str()?).ok()?;
+
+    self.resources.get(&id.key()).copied()
+  }
+
+  // The resources of `kind` whose `property` refers to `resource`
+  pub fn referenced_by(&self, resource: &Resource, kind: &str, property: &Property) -> &[&'a Resource] {
+    self.referrers
+      .get(&(kind.to_owned(), property.clone()))
+      .and_then(|targets| targets.get(&resource.id().key()))
+      .map_or(&[], |referrers| referrers.as_slice())
+  }
+}
+
+fn collect_referenced_by(expression: &Expression, found: &mut impl FnMut(&str, &Property)) {
+  match expression {
+    Expression::ReferencedBy(kind, property) => found(kind, property),
+    Expression::Quantified(_, inner) | Expression::Call(_, inner) | Expression::Reference(inner, _) => collect_referenced_by(inner, found),
+    Expression::Property(_) => {}
+  }
+}
+
+fn collect_keys(value: &Value, keys: &mut Vec<String>) {
+  match value {
+    Value::Array(values) => values.iter().for_each(|v| collect_keys(v, keys)),
+    Value::String(id) => keys.extend(Id::try_from(id.as_str()).ok().map(|id| id.key())),
+    _ => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_source;
+  use serde_json::json;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_candidates() {
+    let source = "azure.* {\n  @id(\"all\") location = \"uksouth\"\n}\n\
+      azure.web-rg.app_service {\n  @id(\"apps\") properties.httpsOnly = true\n}\n\
+      azure.*.app_service.legacy-app {\n  @id(\"legacy\") location = \"westeurope\"\n}\n\
+      azure.data-rg {\n  @id(\"data\") location = \"uksouth\"\n}\n\
+      aws.* {\n  @id(\"aws\") location = \"uksouth\"\n}\n";
+    let rules = parse_source(source).unwrap();
+    let index = RuleIndex::new(&rules);
+
+    let resource = Resource::try_from(json!({
+      "id": "/subscriptions/00d88f1a/resourceGroups/web-rg/providers/Microsoft.Web/sites/legacy-app",
+    })).unwrap();
+    let (candidates, others) = index.partition(&resource);
+    let ids: Vec<String> = candidates.iter().map(|rule| rule.id()).collect();
+
+    assert_eq!(ids, vec!["all", "apps", "legacy"]);
+    assert!(candidates.iter().all(|rule| resource.selector_applies(&rule.selector)));
+    assert_eq!(others.iter().map(|rule| rule.id()).collect::<Vec<_>>(), vec!["data", "aws"]);
+    assert!(others.iter().all(|rule| !resource.selector_applies(&rule.selector)));
+  }
+
+  #[test]
+  fn test_resource_index() {
+    let kind = "Microsoft.Network/privateEndpoints";
+    let path = "properties.privateLinkServiceConnections[*].properties.privateLinkServiceId";
+    let source = format!("azure.* {{\n  count(referenced_by({}, {})) > 0\n}}\n", kind, path);
+    let rules = parse_source(&source).unwrap();
+    let storage = "/subscriptions/00d88f1a/resourceGroups/data-rg/providers/Microsoft.Storage/storageAccounts/data";
+    let endpoint = |name: &str, targets: Value| json!({
+      "id": format!("/subscriptions/00d88f1a/resourceGroups/data-rg/providers/microsoft.network/privateendpoints/{}", name),
+      "properties": { "privateLinkServiceConnections": targets },
+    });
+    let resources: Vec<Resource> = vec![
+      json!({ "id": storage }),
+      endpoint("pe-1", json!([
+        { "properties": { "privateLinkServiceId": storage.to_uppercase() } },
+        { "properties": { "privateLinkServiceId": storage } },
+      ])),
+      endpoint("pe-2", json!([])),
+    ].into_iter().map(|r| Resource::try_from(r).unwrap()).collect();
+    let index = ResourceIndex::new(&resources, &rules);
+    let property = Property::try_from(path).unwrap();
+
+    assert_eq!(index.get(&json!(storage.to_uppercase())).map(|r| r.arm_id()), Some(storage));
+    assert!(index.get(&json!("/subscriptions/00d88f1a/resourceGroups/data-rg/providers/Microsoft.Storage/storageAccounts/missing")).is_none());
+
+    let referrers: Vec<&str> = index.referenced_by(&resources[0], kind, &property).iter().map(|r| r.arm_id()).collect();
+    assert_eq!(referrers, vec![resources[1].arm_id()]);
+    assert!(index.referenced_by(&resources[1], kind, &property).is_empty());
+  }
+}
@@ -0,0 +1,32 @@
+//
+// cloud-lint evaluates rule files against cloud resources. The `cloud-lint` binary is
+// one front-end; the same engine can be embedded elsewhere:
+//
+//   let rules = cloud_lint::parse_rules("rules.cloud")?;
+//   let resources = vec![Resource::try_from(json)?];
+//   let results = Engine::new().evaluate(&resources, &rules);
+//   let score = Breakdown::from_results(&results).overall.percentage();
+//
+
+pub mod azurerm;
+pub mod baseline;
+pub mod network;
+pub mod parser;
+pub mod remediation;
+pub mod report;
+pub mod rules;
+pub mod scoring;
+pub mod waivers;
+
+mod engine;
+mod index;
+
+pub use azurerm::Resource;
+pub use baseline::{ parse_baseline, write_baseline, Baseline };
+pub use engine::{ Engine, Evaluation, Outcome, ResourceCompliance, ResourceGroupCompliance };
+pub use parser::{ parse_rules, parse_source, ParseError };
+pub use remediation::{ Change, Remediation };
+pub use report::Format;
+pub use rules::Rule;
+pub use scoring::{ Breakdown, Score };
+pub use waivers::{ parse_waivers, Waiver };
@@ -0,0 +1,116 @@
+use log::kv::{ Error, Key, Value, VisitSource };
+use log::{ Level, LevelFilter, Log, Metadata, Record };
+use serde_json::{ Map, Value as Json };
+use std::io::Write;
+
+// Writes log records to stderr, keeping them apart from the report on stdout. Text lines
+// look like `debug: evaluated rule rule=app-location outcome=noncompliant`; JSON lines
+// carry the same fields for ingestion.
+struct Logger {
+  level: LevelFilter,
+  json: bool,
+}
+
+// -q shows errors only, the default adds warnings, and each -v adds a level
+pub fn level(verbosity: u64, quiet: bool) -> LevelFilter {
+  match (quiet, verbosity) {
+    (true, _) => LevelFilter::Error,
+    (false, 0) => LevelFilter::Warn,
+    (false, 1) => LevelFilter::Info,
+    (false, 2) => LevelFilter::Debug,
+    (false, _) => LevelFilter::Trace,
+  }
+}
+
+pub fn init(level: LevelFilter, json: bool) {
+  if log::set_logger(Box::leak(Box::new(Logger { level, json }))).is_ok() {
+    log::set_max_level(level);
+  }
+}
+
+struct Fields(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+  fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+    self.0.push((key.to_string(), value.to_string()));
+    Ok(())
+  }
+}
+
+fn format_line(record: &Record, fields: Vec<(String, String)>, json: bool) -> String {
+  if json {
+    let mut line = Map::new();
+
+    line.insert("level".to_owned(), Json::from(record.level().as_str().to_ascii_lowercase()));
+    line.insert("target".to_owned(), Json::from(record.target()));
+    line.insert("message".to_owned(), Json::from(record.args().to_string()));
+
+    for (key, value) in fields {
+      line.insert(key, Json::from(value));
+    }
+
+    Json::Object(line).to_string()
+  } else {
+    let level = record.level().as_str().to_ascii_lowercase();
+    let fields: String = fields.iter().map(|(key, value)| format!(" {}={}", key, value)).collect();
+
+    format!("{}: {}{}", level, record.args(), fields)
+  }
+}
+
+impl Log for Logger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= self.level
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) {
+      return;
+    }
+
+    // Dependencies (e.g. the HTTP client) are only interesting when tracing
+    if !record.target().starts_with("cloud_lint") && record.level() < Level::Trace {
+      return;
+    }
+
+    let mut fields = Fields(Vec::new());
+    let _ = record.key_values().visit(&mut fields);
+
+    let _ = writeln!(std::io::stderr(), "{}", format_line(record, fields.0, self.json));
+  }
+
+  fn flush(&self) {
+    let _ = std::io::stderr().flush();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_format_line() {
+    let fields = vec![("rule".to_owned(), "app-location".to_owned()), ("outcome".to_owned(), "error".to_owned())];
+    let record = Record::builder()
+      .level(Level::Debug)
+      .target("cloud_lint::engine")
+      .args(format_args!("evaluated rule"))
+      .build();
+
+    assert_eq!(
+      format_line(&record, fields.clone(), false),
+      "debug: evaluated rule rule=app-location outcome=error"
+    );
+    assert_eq!(
+      format_line(&record, fields, true),
+      r#"{"level":"debug","message":"evaluated rule","outcome":"error","rule":"app-location","target":"cloud_lint::engine"}"#
+    );
+  }
+
+  #[test]
+  fn test_level() {
+    assert_eq!(level(0, false), LevelFilter::Warn);
+    assert_eq!(level(2, false), LevelFilter::Debug);
+    assert_eq!(level(2, true), LevelFilter::Error);
+  }
+}
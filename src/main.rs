@@ -3,177 +3,479 @@
 // TODO:
 //   * Read rules from file (Y)
 //   * Hierarchical rules (most specific rule is used for conflicts)
-//   * Write JUNIT output
+//   * Write JUNIT output (Y)
 //   * regex match condition (Y)
 //
 
-mod azurerm;
-mod parser;
-mod rules;
+mod logger;
 
-use azurerm::Resource;
-use rules::Rule;
+use cloud_lint::azurerm;
+use cloud_lint::{
+  parse_baseline, parse_rules, parse_waivers, remediation, report, write_baseline, Baseline, Breakdown, Engine,
+  Format, Outcome, Remediation, ResourceCompliance, ResourceGroupCompliance, Rule,
+};
+use cloud_lint::rules::Severity;
+use std::convert::TryFrom;
+use std::io::{ BufRead, Write };
 
 use clap;
 
-struct ResourceCompliance {
-  resource_name: String,
-  resource_type: String,
+const EXIT_CODES: &str = "EXIT CODES:
+    0    Compliant, or within the --fail-on and --fail-under thresholds
+    1    Non-compliant: the results breached --fail-on or --fail-under
+    2    Usage error: invalid command-line arguments
+    3    Parse error: the rule, waiver, baseline or resources file couldn't be read or parsed
+    4    Cloud API error: Azure couldn't be reached, responded with an error, or rejected a --fix change
+    5    Output error: a report or baseline file couldn't be written";
 
-  compliant_rules: Vec<Rule>,
-  noncompliant_rules: Vec<Rule>,
+#[derive(Debug)]
+enum ClientLintError {
+  CommandLineError,
+  ParserError,
+  CloudError,
+  OutputError,
+  NonCompliant,
+}
+
+impl ClientLintError {
+  // See `EXIT_CODES`
+  fn exit_code(&self) -> i32 {
+    match self {
+      ClientLintError::NonCompliant => 1,
+      ClientLintError::CommandLineError => 2,
+      ClientLintError::ParserError => 3,
+      ClientLintError::CloudError => 4,
+      ClientLintError::OutputError => 5,
+    }
+  }
 }
 
-fn evaluate_rules(resource: &Resource, rules: &Vec<Rule>) -> ResourceCompliance {
-  let resource_name = resource.name();
-  let resource_kind = resource.kind();
+type ApplicationResult = Result<(Vec<ResourceCompliance>, ResourceGroupCompliance), ClientLintError>;
 
-  let mut compliant_rules: Vec<Rule> = Vec::new();
-  let mut noncompliant_rules: Vec<Rule> = Vec::new();
-  let mut nonapplicable_rules: Vec<Rule> = Vec::new();
+fn cloud_error(e: azurerm::ClientError) -> ClientLintError {
+  log::error!("{}", e);
+  ClientLintError::CloudError
+}
 
-  for rule in rules {
-    if resource.selector_applies(&rule.selector) {
-      let prop = resource.get_property(&rule.property);
+fn azure_lint(client: &azurerm::Client, rules: &[Rule], engine: Engine) -> ApplicationResult {
+  let not_found = |what: &str| {
+    log::error!("No {} found", what);
+    ClientLintError::CloudError
+  };
 
-      if let Some(value) = prop.as_str() {
-        if rule.condition.is_compliant(value) {
-          compliant_rules.push(rule.clone());
-          continue;
-        } 
-      }
+  let subscriptions = client.get_subscriptions().map_err(cloud_error)?;
+  let subscription_id = subscriptions.first().ok_or_else(|| not_found("subscriptions"))?;
 
-      noncompliant_rules.push(rule.clone());
-    } else {
-      println!(
-        "{} {{ {:?} {:?} }} does not apply to {} in {} ({})",
-        rule.selector, rule.property, rule.condition,
-        resource.name(), resource.group(), resource.kind()
-      );
-      nonapplicable_rules.push(rule.clone());
+  let resource_groups = client.get_resource_groups(subscription_id).map_err(cloud_error)?;
+  let resource_group_name = resource_groups.first().ok_or_else(|| not_found("resource groups"))?;
+  let resources = client.get_resources(subscription_id, resource_group_name).map_err(cloud_error)?;
+
+  log::info!(
+    subscription = subscription_id.as_str(), group = resource_group_name.as_str(), resources = resources.len();
+    "fetched resources"
+  );
+
+  let compliance = engine.evaluate(&resources, rules);
+  let group_compliance = ResourceGroupCompliance::from_results(&compliance);
+
+  Ok((compliance, group_compliance))
+}
+
+// Lints resources read from a local file rather than fetched from Azure
+fn local_lint(path: &str, rules: &[Rule], engine: Engine) -> ApplicationResult {
+  let resources = azurerm::parse_resources(path).map_err(|e| {
+    log::error!("{}", e);
+    ClientLintError::ParserError
+  })?;
+
+  log::info!(file = path, resources = resources.len(); "read resources");
+
+  let compliance = engine.evaluate(&resources, rules);
+  let group_compliance = ResourceGroupCompliance::from_results(&compliance);
+
+  Ok((compliance, group_compliance))
+}
+
+// The reasons the results breach the `--fail-on` and `--fail-under` thresholds, if any
+fn threshold_breaches(
+  group_compliance: &ResourceGroupCompliance,
+  score: f64,
+  fail_on: Option<Severity>,
+  fail_under: Option<f64>,
+) -> Vec<String> {
+  let mut breaches = Vec::new();
+
+  if let (Some(threshold), Some(severity)) = (fail_on, group_compliance.highest_severity) {
+    if severity >= threshold {
+      breaches.push(format!("found a {} severity violation (--fail-on {})", severity, threshold));
     }
   }
 
-  ResourceCompliance {
-    resource_name: resource_name.to_owned(),
-    resource_type: resource_kind.to_owned(),
-    compliant_rules,
-    noncompliant_rules,
+  if let Some(threshold) = fail_under {
+    if score < threshold {
+      breaches.push(format!("compliance score {:.1}% is below {}% (--fail-under)", score, threshold));
+    }
   }
+
+  breaches
 }
 
-#[derive(Default)]
-struct ResourceGroupCompliance {
-  resource_count: usize,         // the total number of resources evaluated
-  compliant_resources: usize,    // the number of completely compliant resources
-  noncompliant_resources: usize, // the number of rules non-compliant with at least one rule
+fn text_report(compliance: &[ResourceCompliance], group_compliance: &ResourceGroupCompliance, breakdown: &Breakdown) -> String {
+  let mut lines: Vec<String> = Vec::new();
 
-  evaluated_rules: usize, // the total number of rule evaluations (e.g. 1 rule * 3 resources = 3 evaluations)
-  compliant_rule_evaluations: usize, // the total number of rules that evaluated as compliant
-  noncompliant_rule_evaluations: usize, // the total number of rules that evaluated as noncompliant
-}
+  // Waived and errored evaluations are excluded from the score; baselined ones still count against it
+  lines.push(format!(
+    "Compliance score is {:.0}% ({}/{} rules compliant, {} waived, {} baselined, {} errors, {} not applicable, across {} resources)",
+    breakdown.overall.percentage(),
+    group_compliance.compliant_rule_evaluations,
+    group_compliance.compliant_rule_evaluations
+      + group_compliance.noncompliant_rule_evaluations
+      + group_compliance.baselined_rule_evaluations,
+    group_compliance.waived_rule_evaluations,
+    group_compliance.baselined_rule_evaluations,
+    group_compliance.error_rule_evaluations,
+    group_compliance.nonapplicable_rule_evaluations,
+    group_compliance.resource_count,
+  ));
 
-fn accumulate_group_compliance(
-  group_compliance: ResourceGroupCompliance,
-  resource_compliance: &ResourceCompliance,
-) -> ResourceGroupCompliance {
-  let is_compliant = resource_compliance.noncompliant_rules.len() > 0;
-
-  ResourceGroupCompliance {
-    resource_count: group_compliance.resource_count + 1,
-    compliant_resources: group_compliance.compliant_resources + (if is_compliant { 1 } else { 0 }),
-    noncompliant_resources: group_compliance.noncompliant_resources
-      + (if is_compliant { 0 } else { 1 }),
-
-    evaluated_rules: group_compliance.evaluated_rules
-      + resource_compliance.compliant_rules.len()
-      + resource_compliance.noncompliant_rules.len(),
-    compliant_rule_evaluations: group_compliance.compliant_rule_evaluations
-      + resource_compliance.compliant_rules.len(),
-    noncompliant_rule_evaluations: group_compliance.noncompliant_rule_evaluations
-      + resource_compliance.noncompliant_rules.len(),
+  for (heading, scores) in breakdown.sections() {
+    lines.push(format!("Compliance by {}:", heading));
+
+    for (key, score) in scores {
+      lines.push(format!("    {}: {:.0}% ({}/{} compliant)", key, score.percentage(), score.compliant, score.compliant + score.noncompliant + score.baselined));
+    }
   }
+
+  for resource in compliance {
+    let mut report = |heading: &str, matches: fn(&Outcome) -> bool| {
+      if resource.count(matches) == 0 {
+        return;
+      }
+
+      lines.push(format!("Resource {} ({}) {}:", resource.resource_name, resource.resource_type, heading));
+
+      for evaluation in resource.evaluations.iter().filter(|evaluation| matches(&evaluation.outcome)) {
+        let failure = format!("expected {}, found {}", evaluation.rule, evaluation.found());
+
+        match &evaluation.outcome {
+          Outcome::Waived { waiver, .. } => {
+            lines.push(format!("    {} (waived until {}: {})", failure, waiver.expires, waiver.justification))
+          }
+          Outcome::Error { reason } => lines.push(format!("    {} ({})", failure, reason)),
+          _ => lines.push(format!("    {}", failure)),
+        }
+
+        if let Some(remediation) = Remediation::for_evaluation(evaluation, &resource.resource_id) {
+          if let Some(guidance) = &remediation.guidance {
+            lines.push(format!("      remediation: {}", guidance));
+          }
+
+          if let Some(change) = &remediation.change {
+            lines.push(format!("      fix: {}", change.az_command()));
+          }
+        }
+      }
+    };
+
+    report("is not compliant with the following rules", |o| matches!(o, Outcome::NonCompliant { .. }));
+    report("has waived rules", |o| matches!(o, Outcome::Waived { .. }));
+    report("could not be evaluated against the following rules", |o| matches!(o, Outcome::Error { .. }));
+  }
+
+  lines.iter().map(|line| format!("{}\n", line)).collect()
 }
 
-#[derive(Debug)]
-enum ClientLintError {
-  CommandLineError,
-  ParserError,
-  CloudError,
+fn confirm(prompt: &str) -> bool {
+  eprint!("{} [y/N] ", prompt);
+  let _ = std::io::stderr().flush();
+
+  let mut answer = String::new();
+  let _ = std::io::stdin().lock().read_line(&mut answer);
+
+  matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
 }
 
-type ApplicationResult = Result<(Vec<ResourceCompliance>, ResourceGroupCompliance), ClientLintError>;
+// Plans, confirms and applies `--fix` changes, printing a summary of what was applied. All of
+// this goes to stderr, alongside the prompt, so a report on stdout stays parseable.
+fn fix(client: &azurerm::Client, compliance: &[ResourceCompliance], dry_run: bool, assume_yes: bool) -> Result<(), ClientLintError> {
+  let changes = remediation::fixes(compliance);
+
+  if changes.is_empty() {
+    eprintln!("Nothing to fix: no violations of @fixable rules");
+    return Ok(());
+  }
+
+  eprintln!("Planned fixes:");
+  for change in &changes {
+    eprintln!("    PATCH {} {}", change.resource_id, change.patch_body());
+  }
+
+  if dry_run {
+    eprintln!("Dry run: {} changes not applied", changes.len());
+    return Ok(());
+  }
+
+  if !assume_yes && !confirm(&format!("Apply {} changes?", changes.len())) {
+    eprintln!("No changes applied");
+    return Ok(());
+  }
 
-fn azure_lint(rules: &Vec<Rule>, tenant_id: &str, client_id: &str, client_secret: &str) -> ApplicationResult {
-  let client = azurerm::Client::new(tenant_id, client_id, client_secret);
-  let subscriptions = client.get_subscriptions();
-  let subscription_id = &subscriptions[0];
+  let summary = remediation::apply(client, changes);
 
-  let resource_groups = client.get_resource_groups(&subscription_id);
-  let resource_group_name = &resource_groups[0];
-  let resources = client.get_resources(subscription_id, resource_group_name);
+  eprintln!("Applied {} changes, {} failed", summary.applied.len(), summary.failed.len());
+  for (change, e) in &summary.failed {
+    eprintln!("    {} ({}): {}", change.resource_id, change.path, e);
+  }
 
-  let compliance = resources
-    .iter()
-    .map(|r| evaluate_rules(&r, &rules))
-    .collect::<Vec<ResourceCompliance>>();
+  if summary.failed.is_empty() { Ok(()) } else { Err(ClientLintError::CloudError) }
+}
 
-  let group_compliance = compliance.iter().fold(
-    ResourceGroupCompliance::default(),
-    accumulate_group_compliance,
+// `source` is the local file the resources were read from, if any
+fn render_report(
+  format: Format,
+  compliance: &[ResourceCompliance],
+  group_compliance: &ResourceGroupCompliance,
+  breakdown: &Breakdown,
+  rules: &[Rule],
+  source: Option<&str>,
+) -> String {
+  match format {
+    Format::Text => text_report(compliance, group_compliance, breakdown),
+    Format::Junit => report::junit::render(compliance),
+    Format::Sarif => report::sarif::render(compliance, rules, source),
+    Format::Json => report::json::render(compliance, chrono::Utc::now()),
+    Format::Html => report::html::render(compliance, chrono::Utc::now()),
+    Format::Markdown => report::markdown::render(compliance),
+  }
+}
+
+fn azure(subcmd: &clap::ArgMatches) -> Result<(), ClientLintError> {
+  // Global arguments propagate down to the subcommand, wherever they were given
+  logger::init(
+    logger::level(subcmd.occurrences_of("verbose"), subcmd.is_present("quiet")),
+    subcmd.value_of("log-format") == Some("json"),
   );
 
-  Ok((compliance, group_compliance))
+  let fail_on = subcmd.value_of("fail-on").map(Severity::try_from).transpose().map_err(|e| {
+    log::error!("{}", e);
+    ClientLintError::CommandLineError
+  })?;
+  let format = subcmd.value_of("format").map(Format::try_from).transpose().map_err(|e| {
+    log::error!("{}", e);
+    ClientLintError::CommandLineError
+  })?.unwrap_or(Format::Text);
+  let fail_under = subcmd.value_of("fail-under").and_then(|v| v.parse::<f64>().ok());
+
+  let rules = parse_rules(subcmd.value_of("FILE").ok_or(ClientLintError::CommandLineError)?).map_err(|e| {
+    log::error!("{}", e);
+    ClientLintError::ParserError
+  })?;
+  let engine = Engine::new()
+    .with_waivers(subcmd.value_of("waivers").map(parse_waivers).transpose().map_err(|e| {
+      log::error!("{}", e);
+      ClientLintError::ParserError
+    })?.unwrap_or_default())
+    .with_baseline(subcmd.value_of("baseline").map(parse_baseline).transpose().map_err(|e| {
+      log::error!("{}", e);
+      ClientLintError::ParserError
+    })?.unwrap_or_default());
+
+  // Local resources are linted without signing in to Azure (so without --fix)
+  let source = subcmd.value_of("resources");
+  let (compliance, group_compliance, client) = match source {
+    Some(path) => {
+      let (compliance, group_compliance) = local_lint(path, &rules, engine)?;
+
+      (compliance, group_compliance, None)
+    }
+    None => {
+      let client = azurerm::Client::new(
+        subcmd.value_of("tenant-id").ok_or(ClientLintError::CommandLineError)?,
+        subcmd.value_of("client-id").ok_or(ClientLintError::CommandLineError)?,
+        subcmd.value_of("client-secret").ok_or(ClientLintError::CommandLineError)?,
+      ).map_err(cloud_error)?;
+      let (compliance, group_compliance) = azure_lint(&client, &rules, engine)?;
+
+      (compliance, group_compliance, Some(client))
+    }
+  };
+
+  if let Some(path) = subcmd.value_of("write-baseline") {
+    write_baseline(path, &Baseline::from_results(&compliance)).map_err(|e| {
+      log::error!("{}", e);
+      ClientLintError::OutputError
+    })?;
+  }
+
+  if let Some(path) = subcmd.value_of("remediation-script") {
+    std::fs::write(path, remediation::script(&compliance)).map_err(|e| {
+      log::error!("Failed to write remediation script: {}", e);
+      ClientLintError::OutputError
+    })?;
+  }
+
+  for resource in &compliance {
+    for (rule, waiver) in &resource.expired_waivers {
+      log::warn!(
+        rule = rule.id().as_str(), resource = resource.resource_id.as_str(), expires:% = waiver.expires;
+        "waiver has expired"
+      );
+    }
+  }
+
+  let breakdown = Breakdown::from_results(&compliance);
+
+  let rendered = render_report(format, &compliance, &group_compliance, &breakdown, &rules, source);
+
+  match subcmd.value_of("output") {
+    Some(path) => std::fs::write(path, rendered).map_err(|e| {
+      log::error!("Failed to write report: {}", e);
+      ClientLintError::OutputError
+    })?,
+    None => print!("{}", rendered),
+  }
+
+  if let (true, Some(client)) = (subcmd.is_present("fix"), &client) {
+    fix(client, &compliance, subcmd.is_present("dry-run"), subcmd.is_present("yes"))?;
+  }
+
+  let breaches = threshold_breaches(&group_compliance, breakdown.overall.percentage(), fail_on, fail_under);
+
+  for breach in &breaches {
+    log::error!("{}", breach);
+  }
+
+  if breaches.is_empty() { Ok(()) } else { Err(ClientLintError::NonCompliant) }
+}
+
+fn main() {
+  std::process::exit(match run() {
+    Ok(()) => 0,
+    Err(e) => e.exit_code(),
+  });
 }
 
-fn main() -> Result<(), ClientLintError> {
+fn run() -> Result<(), ClientLintError> {
   use clap::{App, Arg, SubCommand};
 
   let matches = App::new("cloud-lint")
     .version("0.1")
     .author("T. Bladen-Hovell")
     .about("Lint your cloud resources")
+    .after_help(EXIT_CODES)
+    .arg(Arg::with_name("verbose").short("v").multiple(true).global(true).help("Log progress (-vv for each rule evaluation)"))
+    .arg(Arg::with_name("quiet").short("q").global(true).conflicts_with("verbose").help("Only log errors"))
+    .arg(
+      Arg::with_name("log-format").long("log-format").takes_value(true).global(true)
+        .possible_values(&["text", "json"]).default_value("text")
+        .help("Format of the log lines written to stderr"),
+    )
     .subcommand(
       SubCommand::with_name("azure")
         .about("Inspect an Azure resource group")
         .arg(Arg::with_name("FILE").index(1).required(true))
-        .arg(Arg::with_name("tenant-id").long("tenant-id").takes_value(true).required(true))
-        .arg(Arg::with_name("client-id").long("client-id").takes_value(true).required(true))
-        .arg(Arg::with_name("client-secret").long("client-secret").takes_value(true).required(true)),
+        .arg(
+          Arg::with_name("format").long("format").takes_value(true)
+            .possible_values(&["text", "junit", "sarif", "json", "html", "markdown"]).default_value("text")
+            .help("Format of the report written to stdout or --output"),
+        )
+        .arg(Arg::with_name("output").long("output").short("o").takes_value(true).help("File to write the report to"))
+        .arg(Arg::with_name("waivers").long("waivers").takes_value(true).help("JSON file of approved rule waivers"))
+        .arg(
+          Arg::with_name("baseline").long("baseline").takes_value(true)
+            .help("Results of a previous run; only violations not in it are reported"),
+        )
+        .arg(
+          Arg::with_name("write-baseline").long("write-baseline").takes_value(true)
+            .help("Write this run's violations to a file for use with --baseline"),
+        )
+        .arg(
+          Arg::with_name("remediation-script").long("remediation-script").takes_value(true)
+            .help("Write a shell script of `az` commands fixing the violations"),
+        )
+        .arg(
+          Arg::with_name("resources").long("resources").takes_value(true)
+            .help("JSON file of resources (e.g. from `az resource list`) to lint instead of fetching them from Azure"),
+        )
+        .arg(Arg::with_name("fix").long("fix").conflicts_with("resources").help("PATCH resources violating @fixable rules"))
+        .arg(Arg::with_name("dry-run").long("dry-run").requires("fix").help("Show the changes --fix would make"))
+        .arg(Arg::with_name("yes").long("yes").short("y").requires("fix").help("Apply --fix changes without asking"))
+        .arg(
+          Arg::with_name("fail-on").long("fail-on").takes_value(true)
+            .possible_values(&["low", "medium", "high", "critical"])
+            .help("Exit with 1 if there are new violations of at least this severity"),
+        )
+        .arg(
+          Arg::with_name("fail-under").long("fail-under").takes_value(true).value_name("percent")
+            .validator(|v| v.parse::<f64>().map(|_| ()).map_err(|_| "expected a percentage".to_owned()))
+            .help("Exit with 1 if the compliance score is below this percentage"),
+        )
+        .arg(Arg::with_name("tenant-id").long("tenant-id").takes_value(true).required_unless("resources"))
+        .arg(Arg::with_name("client-id").long("client-id").takes_value(true).required_unless("resources"))
+        .arg(Arg::with_name("client-secret").long("client-secret").takes_value(true).required_unless("resources"))
+        .after_help(EXIT_CODES),
     )
-    .get_matches();
-
-  let (compliance, group_compliance) = match matches.subcommand() {
-    ("azure", Some(subcmd)) => azure_lint(
-      &parser::parse_rules(subcmd.value_of("FILE").ok_or(ClientLintError::CommandLineError)?).ok_or(ClientLintError::ParserError)?,
-      subcmd.value_of("tenant-id").ok_or(ClientLintError::CommandLineError)?,
-      subcmd.value_of("client-id").ok_or(ClientLintError::CommandLineError)?,
-      subcmd.value_of("client-secret").ok_or(ClientLintError::CommandLineError)?,
-    ),
+    .get_matches_safe()
+    .map_err(|e| {
+      // --help and --version aren't errors
+      if !e.use_stderr() {
+        e.exit();
+      }
+
+      eprintln!("{}", e.message);
+      ClientLintError::CommandLineError
+    })?;
+
+  match matches.subcommand() {
+    ("azure", Some(subcmd)) => azure(subcmd),
     _ => Err(ClientLintError::CommandLineError),
-  }?;
+  }
+}
 
-  println!(
-    "Compliance score is {:.0}% ({}/{} rules compliant across {} resources",
-    group_compliance.compliant_rule_evaluations as f64 / group_compliance.evaluated_rules as f64
-      * 100.0,
-    group_compliance.compliant_rule_evaluations,
-    group_compliance.evaluated_rules,
-    group_compliance.resource_count,
-  );
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  for resource in compliance {
-    if resource.noncompliant_rules.len() > 0 {
-      println!(
-        "Resource {} ({}) is not compliant with the following rules:",
-        resource.resource_name, resource.resource_type
-      );
+  #[test]
+  fn test_threshold_breaches() {
+    let group_compliance = ResourceGroupCompliance {
+      highest_severity: Some(Severity::High),
+      ..ResourceGroupCompliance::default()
+    };
 
-      for rule in resource.noncompliant_rules {
-        println!("    {}", rule);
-      }
-    }
+    assert!(threshold_breaches(&group_compliance, 90.0, None, None).is_empty());
+    assert!(threshold_breaches(&group_compliance, 90.0, Some(Severity::Critical), Some(80.0)).is_empty());
+    assert_eq!(
+      threshold_breaches(&group_compliance, 75.0, Some(Severity::Medium), Some(80.0)),
+      vec![
+        "found a high severity violation (--fail-on medium)",
+        "compliance score 75.0% is below 80% (--fail-under)",
+      ]
+    );
+    assert!(threshold_breaches(&ResourceGroupCompliance::default(), 100.0, Some(Severity::Low), None).is_empty());
   }
 
-  Ok(())
+  #[test]
+  fn test_sarif_locates_local_resources() {
+    let path = std::env::temp_dir().join(format!("cloud-lint-resources-{}.json", std::process::id()));
+    let resources = serde_json::json!({ "value": [
+      { "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app", "location": "westeurope" },
+    ] });
+    std::fs::write(&path, resources.to_string()).unwrap();
+
+    let path = path.to_str().unwrap();
+    let rules = cloud_lint::parse_source("azure.* {\n  location = \"uksouth\"\n}\n").unwrap();
+    let (compliance, group_compliance) = local_lint(path, &rules, Engine::new()).unwrap();
+    let breakdown = Breakdown::from_results(&compliance);
+    let sarif = render_report(Format::Sarif, &compliance, &group_compliance, &breakdown, &rules, Some(path));
+    std::fs::remove_file(path).unwrap();
+
+    let sarif: serde_json::Value = serde_json::from_str(&sarif).unwrap();
+    let location = &sarif["runs"][0]["results"][0]["locations"][0];
+    assert_eq!(location["physicalLocation"]["artifactLocation"]["uri"], path);
+    assert_eq!(
+      location["logicalLocations"][0]["fullyQualifiedName"],
+      "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app"
+    );
+  }
 }
@@ -0,0 +1,180 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::net::IpAddr;
+
+// An IPv4 or IPv6 address range in CIDR notation. A bare address is treated as a
+// single-address network (/32 or /128).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Network {
+  address: IpAddr,
+  prefix: u8,
+}
+
+// Ranges that are not reachable from the internet
+const NON_PUBLIC: &[&str] = &[
+  "0.0.0.0/8",
+  "10.0.0.0/8",
+  "100.64.0.0/10",
+  "127.0.0.0/8",
+  "169.254.0.0/16",
+  "172.16.0.0/12",
+  "192.0.2.0/24",    // documentation
+  "192.168.0.0/16",
+  "198.51.100.0/24", // documentation
+  "203.0.113.0/24",  // documentation
+  "224.0.0.0/4",     // multicast
+  "240.0.0.0/4",     // reserved
+  "::/128",
+  "::1/128",
+  "fc00::/7",
+  "fe80::/10",
+];
+
+// NSG service tags that only ever cover Azure-internal sources
+const NON_PUBLIC_TAGS: &[&str] = &[
+  "VirtualNetwork",
+  "AzureLoadBalancer",
+  "AzurePlatformDNS",
+  "AzurePlatformIMDS",
+  "AzurePlatformLKM",
+];
+
+// Whether an address, CIDR range or service tag reaches outside private space,
+// or `None` when it's neither a known tag nor a valid network
+pub fn is_public(value: &str) -> Option<bool> {
+  if NON_PUBLIC_TAGS.iter().any(|tag| tag.eq_ignore_ascii_case(value.trim())) {
+    return Some(false);
+  }
+
+  Network::try_from(value).ok().map(|n| n.is_public())
+}
+
+impl Network {
+  fn bits(&self) -> u8 {
+    match self.address {
+      IpAddr::V4(_) => 32,
+      IpAddr::V6(_) => 128,
+    }
+  }
+
+  // The first and last address of the range, widened so both families share a type
+  fn range(&self) -> (u128, u128) {
+    let address = match self.address {
+      IpAddr::V4(address) => u128::from(u32::from(address)),
+      IpAddr::V6(address) => u128::from(address),
+    };
+    let host_bits = u32::from(self.bits() - self.prefix);
+    let host_mask = if host_bits == 128 { u128::MAX } else { (1u128 << host_bits) - 1 };
+
+    (address & !host_mask, address | host_mask)
+  }
+
+  fn same_family(&self, other: &Network) -> bool {
+    self.address.is_ipv4() == other.address.is_ipv4()
+  }
+
+  pub fn is_within(&self, other: &Network) -> bool {
+    let ((start, end), (other_start, other_end)) = (self.range(), other.range());
+
+    self.same_family(other) && other_start <= start && end <= other_end
+  }
+
+  pub fn overlaps(&self, other: &Network) -> bool {
+    let ((start, end), (other_start, other_end)) = (self.range(), other.range());
+
+    self.same_family(other) && start <= other_end && other_start <= end
+  }
+
+  // A range is public unless it lies entirely within private, loopback or link-local space
+  pub fn is_public(&self) -> bool {
+    !NON_PUBLIC.iter()
+      .filter_map(|range| Network::try_from(*range).ok())
+      .any(|range| self.is_within(&range))
+  }
+}
+
+impl TryFrom<&str> for Network {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    // NSG rules spell "from anywhere" as `*`, `Internet` or `Any`
+    if ["*", "internet", "any"].contains(&value.trim().to_ascii_lowercase().as_str()) {
+      return Network::try_from("0.0.0.0/0");
+    }
+
+    let mut parts = value.trim().splitn(2, '/');
+    let address: IpAddr = parts.next().unwrap_or("").parse().map_err(|_| "Invalid IP address")?;
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = match parts.next() {
+      Some(prefix) => prefix.parse::<u8>().map_err(|_| "Invalid CIDR prefix length")?,
+      None => bits,
+    };
+
+    if prefix > bits {
+      return Err("CIDR prefix length is too long");
+    }
+
+    Ok(Self { address, prefix })
+  }
+}
+
+impl fmt::Display for Network {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}/{}", self.address, self.prefix)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn network(value: &str) -> Network {
+    Network::try_from(value).unwrap()
+  }
+
+  #[test]
+  fn test_within() {
+    assert!(network("10.1.0.0/16").is_within(&network("10.0.0.0/8")));
+    assert!(network("10.1.2.3").is_within(&network("10.0.0.0/8")));
+    assert!(!network("0.0.0.0/0").is_within(&network("10.0.0.0/8")));
+    assert!(!network("fd00::/64").is_within(&network("10.0.0.0/8")));
+    assert!(network("fd00::1").is_within(&network("fd00::/8")));
+  }
+
+  #[test]
+  fn test_overlaps() {
+    assert!(network("10.0.0.0/16").overlaps(&network("10.0.255.0/24")));
+    assert!(network("0.0.0.0/0").overlaps(&network("192.168.1.0/24")));
+    assert!(!network("10.0.0.0/16").overlaps(&network("10.1.0.0/16")));
+  }
+
+  #[test]
+  fn test_is_public() {
+    assert!(network("51.140.1.1").is_public());
+    assert!(network("0.0.0.0/0").is_public());
+    assert!(!network("172.20.0.0/16").is_public());
+    assert!(!network("192.168.0.10").is_public());
+    assert!(!network("fe80::1").is_public());
+    assert!(network("2a01:111::1").is_public());
+    assert!(network("*").is_public());
+    assert!(network("Internet").is_public());
+    assert!(!network("224.0.0.251").is_public());
+    assert!(!network("198.51.100.7").is_public());
+  }
+
+  #[test]
+  fn test_service_tags() {
+    assert_eq!(is_public("VirtualNetwork"), Some(false));
+    assert_eq!(is_public("azureloadbalancer"), Some(false));
+    assert_eq!(is_public("Internet"), Some(true));
+    assert_eq!(is_public("10.0.0.0/8"), Some(false));
+    assert_eq!(is_public("Storage"), None);
+  }
+
+  #[test]
+  fn test_invalid() {
+    assert_eq!(Network::try_from("Any"), Network::try_from("0.0.0.0/0"));
+    assert!(Network::try_from("VirtualNetwork").is_err());
+    assert!(Network::try_from("10.0.0.0/33").is_err());
+  }
+}
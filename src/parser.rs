@@ -1,13 +1,20 @@
-use crate::rules::{ Condition, Property, Rule, Selector };
+use crate::network::Network;
+use chrono::Duration;
+use crate::rules::{ Condition, Expression, Function, Metadata, Property, Quantifier, Rule, Selector, Template, Version };
 use nom::branch::alt;
-use nom::bytes::complete::{ tag, take_until, take_while1 };
-use nom::character::complete::{ alpha1, multispace1, space1 };
-use nom::combinator::{ all_consuming, opt, peek };
-use nom::multi::{ many0_count, separated_list };
-use nom::sequence::tuple;
+use nom::bytes::complete::{ tag, take_until, take_while, take_while1 };
+use nom::character::complete::{ digit1, multispace1, space0, space1 };
+use nom::combinator::{ all_consuming, map, map_res, not, opt, peek, verify };
+use nom::error::ErrorKind;
+use nom::multi::{ many0, many0_count, separated_list, separated_nonempty_list };
+use nom::number::complete::double;
+use nom::sequence::{ terminated, tuple };
 use nom::IResult;
 use regex::Regex;
-use std::convert::{ AsRef, TryInto };
+use serde_json::Value;
+use std::collections::{ HashMap, HashSet };
+use std::convert::{ AsRef, TryFrom, TryInto };
+use std::fmt;
 use std::path::Path;
 
 // selector.selector.selector {
@@ -46,45 +53,305 @@ fn selector(i: &str) -> ParseResult<Selector> {
 }
 
 fn property(i: &str) -> ParseResult<Property> {
-  let (rest, property) = alpha1(i)?;
+  let parser = take_while1(|c| char::is_alphanumeric(c) || c == '.' || c == '-' || c == '_' || c == '[' || c == ']' || c == '*');
+  let (rest, property) = parser(i)?;
 
   Ok((rest, property.try_into().unwrap()))
 }
 
+fn quantifier(i: &str) -> ParseResult<'_, Quantifier> {
+  let (rest, quantifier) = alt((tag("any"), tag("all"), tag("none")))(i)?;
+
+  Ok((rest, quantifier.try_into().unwrap()))
+}
+
+// A quantifier only applies to a whole rule (or clause), so it can't be nested inside
+// another quantifier, function call or reference
+fn unquantified_expression(i: &str) -> ParseResult<'_, Expression> {
+  verify(expression, |expression: &Expression| !expression.is_quantified())(i)
+}
+
+fn quantified_expression(i: &str) -> ParseResult<'_, Expression> {
+  let parser = tuple((quantifier, tag("("), space0, unquantified_expression, space0, tag(")")));
+  let (rest, (quantifier, _, _, inner, _, _)) = parser(i)?;
+
+  Ok((rest, Expression::Quantified(quantifier, Box::new(inner))))
+}
+
+fn function(i: &str) -> ParseResult<'_, Function> {
+  let names = alt((
+    tag("len"), tag("count"), tag("lower"), tag("upper"), tag("trim"), tag("normalize_location"), tag("version"),
+    tag("is_public_ip"),
+  ));
+  let (rest, function) = names(i)?;
+
+  Ok((rest, function.try_into().unwrap()))
+}
+
+fn call_expression(i: &str) -> ParseResult<'_, Expression> {
+  let parser = tuple((function, tag("("), space0, unquantified_expression, space0, tag(")")));
+  let (rest, (function, _, _, inner, _, _)) = parser(i)?;
+
+  Ok((rest, Expression::Call(function, Box::new(inner))))
+}
+
+fn reference_expression(i: &str) -> ParseResult<'_, Expression> {
+  let parser = tuple((tag("ref("), space0, unquantified_expression, space0, tag(")."), property));
+  let (rest, (_, _, inner, _, _, property)) = parser(i)?;
+
+  Ok((rest, Expression::Reference(Box::new(inner), property)))
+}
+
+fn kind(i: &str) -> ParseResult<'_, &str> {
+  take_while1(|c| char::is_alphanumeric(c) || c == '.' || c == '-' || c == '_' || c == '/')(i)
+}
+
+fn referenced_by_expression(i: &str) -> ParseResult<'_, Expression> {
+  let parser = tuple((tag("referenced_by("), space0, kind, space0, tag(","), space0, property, space0, tag(")")));
+  let (rest, (_, _, kind, _, _, _, property, _, _)) = parser(i)?;
+
+  Ok((rest, Expression::ReferencedBy(kind.to_owned(), property)))
+}
+
+fn expression(i: &str) -> ParseResult<'_, Expression> {
+  alt((
+    quantified_expression,
+    call_expression,
+    reference_expression,
+    referenced_by_expression,
+    map(property, Expression::Property),
+  ))(i)
+}
+
 fn value(i: &str) -> ParseResult<&str> {
-  let parser = tuple((tag("\""), alpha1, tag("\"")));
+  let parser = tuple((tag("\""), take_while(|c| c != '"'), tag("\"")));
   let (rest, (_, value, _)) = parser(i)?;
 
   Ok((rest, value))
 }
 
+// The right-hand side of an equality: a literal, or something resolved per-resource
+enum Operand<'a> {
+  Literal(&'a str),
+  Template(Template),
+}
+
+// Whether a bare operand is a number, which would otherwise be read as a property path
+// that no resource has
+fn looks_like_number(i: &str) -> bool {
+  i.strip_prefix('-').unwrap_or(i).starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn operand(i: &str) -> ParseResult<'_, Operand<'_>> {
+  if looks_like_number(i) {
+    return Err(nom::Err::Failure((i, ErrorKind::Verify)));
+  }
+
+  let quoted = map_res(value, |value| -> Result<Operand, &'static str> {
+    if Template::is_template(value) {
+      Ok(Operand::Template(value.try_into()?))
+    } else {
+      Ok(Operand::Literal(value))
+    }
+  });
+  let reference = map(property, |property| Operand::Template(property.into()));
+
+  alt((quoted, reference))(i)
+}
+
+fn boolean(i: &str) -> ParseResult<'_, bool> {
+  let (rest, value) = alt((tag("true"), tag("false")))(i)?;
+
+  Ok((rest, value == "true"))
+}
+
+fn equal_bool_rule(i: &str) -> ParseResult<'_, Condition> {
+  let parser = tuple((tag("="), space1, boolean, peek(not(property))));
+  let (rest, (_, _, value, _)) = parser(i)?;
+
+  Ok((rest, Condition::EqualBool(value)))
+}
+
 fn equal_rule(i: &str) -> ParseResult<Condition> {
-  let parser = tuple((tag("="), space1, value));
-  let (rest, (_, _, value)) = parser(i)?;
+  let parser = tuple((tag("="), space1, operand));
+  let (rest, (_, _, operand)) = parser(i)?;
+
+  Ok((rest, match operand {
+    Operand::Literal(value) => Condition::Equal(value.to_owned()),
+    Operand::Template(template) => Condition::EqualTemplate(template),
+  }))
+}
 
-  Ok((rest, Condition::Equal(value.to_owned())))
+fn equal_ignore_case_rule(i: &str) -> ParseResult<'_, Condition> {
+  let parser = tuple((tag("=i"), space1, operand));
+  let (rest, (_, _, operand)) = parser(i)?;
+
+  Ok((rest, match operand {
+    Operand::Literal(value) => Condition::EqualIgnoreCase(value.to_owned()),
+    Operand::Template(template) => Condition::EqualIgnoreCaseTemplate(template),
+  }))
 }
 
-fn regex(i: &str) -> ParseResult<Regex> {
+fn regex(i: &str) -> ParseResult<&str> {
   let parser = tuple((tag("/"), take_until("/"), tag("/")));
   let (rest, (_, pattern, _)) = parser(i)?;
 
-  // TODO: check if valid regex and return custom error if not
-  Ok((rest, Regex::new(pattern).unwrap()))
+  Ok((rest, pattern))
+}
+
+fn match_condition(pattern: &str) -> Result<Condition, &'static str> {
+  if Template::is_template(pattern) {
+    let template = Template::try_from(pattern)?;
+
+    // Substituted values are escaped, so only the static parts can make the pattern invalid
+    let sample = template.render(&|_| Value::from("x"), regex::escape).ok_or("Invalid template")?;
+    Regex::new(&sample).map_err(|_| "Invalid regular expression")?;
+
+    Ok(Condition::MatchTemplate(template))
+  } else {
+    Regex::new(pattern).map(Condition::Match).map_err(|_| "Invalid regular expression")
+  }
 }
 
 fn match_rule(i: &str) -> ParseResult<Condition> {
-  let parser = tuple((tag("~="), space1, regex));
-  let (rest, (_, _, regex)) = parser(i)?;
+  let parser = tuple((tag("~="), space1, map_res(regex, match_condition)));
+  let (rest, (_, _, condition)) = parser(i)?;
+
+  Ok((rest, condition))
+}
+
+fn ordered_op(i: &str) -> ParseResult<'_, &str> {
+  alt((tag("<="), tag(">="), tag("<"), tag(">")))(i)
+}
+
+fn ordered_rule(i: &str) -> ParseResult<'_, Condition> {
+  let parser = tuple((ordered_op, space1, double));
+  let (rest, (op, _, bound)) = parser(i)?;
+
+  let condition = match op {
+    "<=" => Condition::LessOrEqual(bound),
+    ">=" => Condition::GreaterOrEqual(bound),
+    "<" => Condition::LessThan(bound),
+    _ => Condition::GreaterThan(bound),
+  };
+
+  Ok((rest, condition))
+}
+
+// properties.addressPrefix within "10.0.0.0/8"
+fn network_rule(i: &str) -> ParseResult<'_, Condition> {
+  let parser = tuple((alt((tag("within"), tag("overlaps"))), space1, map_res(value, Network::try_from)));
+  let (rest, (op, _, network)) = parser(i)?;
+
+  let condition = match op {
+    "within" => Condition::Within(network),
+    _ => Condition::Overlaps(network),
+  };
+
+  Ok((rest, condition))
+}
+
+// 90d, 12h, 2w
+fn duration(i: &str) -> ParseResult<'_, Duration> {
+  let parser = tuple((digit1, alt((tag("h"), tag("d"), tag("w")))));
+  let (rest, (amount, unit)) = map_res(parser, |(amount, unit): (&str, &str)| {
+    amount.parse::<i64>().map(|amount| (amount, unit))
+  })(i)?;
+
+  let duration = match unit {
+    "h" => Duration::hours(amount),
+    "d" => Duration::days(amount),
+    _ => Duration::weeks(amount),
+  };
+
+  Ok((rest, duration))
+}
+
+// properties.createdTime older_than 90d
+fn date_rule(i: &str) -> ParseResult<'_, Condition> {
+  let parser = tuple((alt((tag("older_than"), tag("within"))), space1, duration));
+  let (rest, (op, _, duration)) = parser(i)?;
+
+  let condition = match op {
+    "older_than" => Condition::OlderThan(duration),
+    _ => Condition::WithinDuration(duration),
+  };
+
+  Ok((rest, condition))
+}
+
+// version(properties.siteConfig.minTlsVersion) >= "1.2", where the quotes are optional
+fn version_rule(i: &str) -> ParseResult<'_, Condition> {
+  let bare = take_while1(|c: char| c.is_ascii_digit() || c == '.');
+  let parser = tuple((ordered_op, space1, map_res(alt((value, bare)), Version::try_from)));
+  let (rest, (op, _, bound)) = parser(i)?;
+
+  let condition = match op {
+    "<=" => Condition::VersionLessOrEqual(bound),
+    ">=" => Condition::VersionGreaterOrEqual(bound),
+    "<" => Condition::VersionLessThan(bound),
+    _ => Condition::VersionGreaterThan(bound),
+  };
+
+  Ok((rest, condition))
+}
+
+fn condition(i: &str) -> ParseResult<'_, Condition> {
+  alt((
+    equal_ignore_case_rule,
+    equal_bool_rule,
+    equal_rule,
+    match_rule,
+    ordered_rule,
+    network_rule,
+    date_rule,
+  ))(i)
+}
+
+// Whether the expression yields `version(...)` values, possibly under a quantifier
+fn is_version(expression: &Expression) -> bool {
+  match expression {
+    Expression::Call(Function::Version, _) => true,
+    Expression::Quantified(_, inner) => is_version(inner),
+    _ => false,
+  }
+}
+
+fn rule_condition(i: &str) -> ParseResult<(Expression, Condition)> {
+  let negation = opt(terminated(tag("not"), space1));
+  let (rest, (expression, _, negation)) = tuple((expression, space1, negation))(i)?;
 
-  Ok((rest, Condition::Match(regex)))
+  // `version(...)` values are only ever compared by version ordering, and nothing else is
+  let (rest, condition) = if is_version(&expression) {
+    version_rule(rest)?
+  } else {
+    alt((condition, element_condition))(rest)?
+  };
+
+  match negation {
+    Some(_) => Ok((rest, (expression, Condition::Not(Box::new(condition))))),
+    None => Ok((rest, (expression, condition))),
+  }
+}
+
+fn refers_to_resources(expression: &Expression) -> bool {
+  match expression {
+    Expression::Reference(..) | Expression::ReferencedBy(..) => true,
+    Expression::Quantified(_, inner) | Expression::Call(_, inner) => refers_to_resources(inner),
+    Expression::Property(_) => false,
+  }
 }
 
-fn rule_condition(i: &str) -> ParseResult<(Property, Condition)> {
-  let parser = tuple((property, space1, alt((equal_rule, match_rule))));
-  let (rest, (property, _, condition)) = parser(i)?;
+// none(properties.securityRules[*]) { properties.access = "Allow" and properties.destinationPortRange = "22" }
+//
+// Clauses are evaluated against each element, so they can't follow references to other resources
+fn element_condition(i: &str) -> ParseResult<'_, Condition> {
+  let clause = verify(rule_condition, |(expression, _): &(Expression, Condition)| !refers_to_resources(expression));
+  let clauses = separated_nonempty_list(tuple((space1, tag("and"), space1)), clause);
+  let parser = tuple((opening_brace, space0, clauses, space0, closing_brace));
+  let (rest, (_, _, clauses, _, _)) = parser(i)?;
 
-  Ok((rest, (property, condition)))
+  Ok((rest, Condition::Element(clauses)))
 }
 
 fn rule_block_line_delim(i: &str) -> ParseResult<&str> {
@@ -94,16 +361,37 @@ fn rule_block_line_delim(i: &str) -> ParseResult<&str> {
   Ok((rest, ""))
 }
 
+fn annotation(i: &str) -> ParseResult<'_, (&str, Option<&str>)> {
+  let parser = tuple((tag("@"), identifier, opt(tuple((tag("("), value, tag(")"))))));
+  let (rest, (_, key, value)) = parser(i)?;
+
+  Ok((rest, (key, value.map(|(_, value, _)| value))))
+}
+
+fn metadata(i: &str) -> ParseResult<'_, Metadata> {
+  let annotations = many0(terminated(annotation, alt((rule_block_line_delim, space1))));
+
+  map_res(annotations, |annotations| {
+    annotations.into_iter().try_fold(Metadata::default(), |metadata, (key, value)| metadata.annotate(key, value))
+  })(i)
+}
+
+fn annotated_rule_condition(i: &str) -> ParseResult<'_, (Metadata, Expression, Condition)> {
+  let (rest, (metadata, (expression, condition))) = tuple((metadata, rule_condition))(i)?;
+
+  Ok((rest, (metadata, expression, condition)))
+}
+
 fn rule_block(i: &str) -> ParseResult<Vec<Rule>> {
-  let rule_condition_lines = separated_list(rule_block_line_delim, rule_condition);
+  let rule_condition_lines = separated_list(rule_block_line_delim, annotated_rule_condition);
   let parser = tuple((selector, space1, opening_brace, multispace1, rule_condition_lines, multispace1, closing_brace));
   let (rest, (selector, _, _, _, conditions, _, _)) = parser(i)?;
 
   Ok((
     rest,
-    conditions.iter()
+    conditions.into_iter()
       .map(
-        |(property, condition)| Rule { selector: selector.clone(), property: property.clone(), condition: condition.clone() }
+        |(metadata, expression, condition)| Rule { selector: selector.clone(), expression, condition, metadata }
       )
       .collect()
   ))
@@ -120,28 +408,265 @@ fn rule_blocks(i: &str) -> ParseResult<Vec<Rule>> {
   Ok((rest, flatten(rule_blocks)))
 }
 
-pub fn parse_rules(path: impl AsRef<Path>) -> Option<Vec<Rule>> {
-  use std::fs::File;
-  use std::io::prelude::*;
+// Templates
+//
+// template naming(prefix) {
+//     name ~= /^${prefix}-/
+// }
+//
+// azure.test-rg.storage {
+//     use naming("st")
+// }
+//
+// Templates are expanded before the rule grammar is parsed: each `use` line is replaced
+// by the template body with `${parameter}` placeholders substituted by the arguments.
+// Arguments are escaped for where they land, so `use naming("st.")` matches a literal
+// `st.` inside `/.../`, and can't break out of a quoted value or onto a new rule line.
+
+const MAX_TEMPLATE_DEPTH: usize = 16;
 
-  let contents = {
-    let mut file = File::open(path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
+struct TemplateDefinition<'a> {
+  parameters: Vec<&'a str>,
+  body: &'a str,
+}
 
-    contents
-  };
+fn identifier(i: &str) -> ParseResult<'_, &str> {
+  take_while1(|c| char::is_alphanumeric(c) || c == '_')(i)
+}
+
+fn template_header(i: &str) -> ParseResult<'_, (&str, Vec<&str>)> {
+  let parameters = separated_list(tuple((space0, tag(","), space0)), identifier);
+  let parser = tuple((tag("template"), space1, identifier, tag("("), space0, parameters, space0, tag(")"), space0, opening_brace));
+  let (rest, (_, _, name, _, _, parameters, _, _, _, _)) = parser(i)?;
+
+  Ok((rest, (name, parameters)))
+}
+
+fn template_use(i: &str) -> ParseResult<'_, (&str, Vec<&str>)> {
+  let arguments = separated_list(tuple((space0, tag(","), space0)), value);
+  let parser = all_consuming(tuple((tag("use"), space1, identifier, tag("("), space0, arguments, space0, tag(")"), space0)));
+  let (rest, (_, _, name, _, _, arguments, _, _, _)) = parser(i)?;
+
+  Ok((rest, (name, arguments)))
+}
+
+// Splits `i` (just after an opening brace) at its matching closing brace. Placeholders
+// such as `${prefix}` are balanced, so they don't end the body early.
+fn template_body(i: &str) -> Option<(&str, &str)> {
+  let mut depth = 0;
+
+  for (index, c) in i.char_indices() {
+    match c {
+      '{' => depth += 1,
+      '}' if depth == 0 => return Some((&i[..index], &i[index + 1..])),
+      '}' => depth -= 1,
+      _ => (),
+    }
+  }
+
+  None
+}
+
+fn collect_templates(source: &str) -> Result<(String, HashMap<&str, TemplateDefinition<'_>>), ParseError> {
+  let mut templates = HashMap::new();
+  let mut remaining = String::new();
+  let mut rest = source;
+
+  while !rest.is_empty() {
+    let line_end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+
+    match template_header(rest.trim_start()) {
+      Ok((after_header, (name, parameters))) => {
+        if let Some(parameter) = parameters.iter().find(|p| RESERVED_PARAMETERS.contains(p)) {
+          return Err(ParseError::Syntax(format!(
+            "Template `{}` parameter `{}` would shadow the resource property of the same name", name, parameter
+          )));
+        }
+
+        let (body, after_body) = template_body(after_header)
+          .ok_or_else(|| ParseError::Syntax(format!("Unterminated template `{}`", name)))?;
+
+        templates.insert(name, TemplateDefinition { parameters, body });
+        rest = after_body;
+      }
+      Err(_) => {
+        remaining.push_str(&rest[..line_end]);
+        rest = &rest[line_end..];
+      }
+    }
+  }
+
+  Ok((remaining, templates))
+}
+
+// Top-level resource properties, which `${...}` in a rule refers to; a parameter with one of
+// these names would shadow the property
+const RESERVED_PARAMETERS: &[&str] = &[
+  "id", "name", "type", "group", "kind", "location", "tags", "properties", "sku", "identity", "plan", "zones",
+  "managedBy", "etag",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Context {
+  Bare,
+  Quoted,
+  Regex,
+  Comment,
+}
+
+fn escape_argument(argument: &str, context: Context) -> Result<String, ParseError> {
+  let invalid = |reason: &str| Err(ParseError::Syntax(format!("Template argument `{}` {}", argument, reason)));
+
+  if argument.contains(['\n', '\r']) {
+    return invalid("spans lines");
+  }
+
+  match context {
+    // The rule grammar has no escape for the `/` delimiter, but the regex engine does
+    Context::Regex => Ok(regex::escape(argument).replace('/', "\\x2F")),
+    Context::Quoted if argument.contains('"') => invalid("can't contain `\"` inside a quoted value"),
+    Context::Quoted if Template::is_template(argument) => invalid("can't contain `${` inside a quoted value"),
+    _ => Ok(argument.to_owned()),
+  }
+}
+
+// Substitutes the template's parameters in `body`, leaving other placeholders (property
+// interpolations such as `${tags.app}`) alone
+fn substitute(body: &str, arguments: &HashMap<&str, &str>) -> Result<String, ParseError> {
+  let mut substituted = String::new();
+  let mut context = Context::Bare;
+  let mut rest = body;
+
+  while let Some(c) = rest.chars().next() {
+    if let Some(placeholder) = rest.strip_prefix("${") {
+      if let Some(end) = placeholder.find('}') {
+        if let Some(argument) = arguments.get(placeholder[..end].trim()) {
+          substituted.push_str(&escape_argument(argument, context)?);
+          rest = &placeholder[end + 1..];
+          continue;
+        }
+      }
+    }
+
+    context = match (context, c) {
+      (Context::Bare, '"') => Context::Quoted,
+      (Context::Bare, '/') if rest.starts_with("//") => Context::Comment,
+      (Context::Bare, '/') if substituted.trim_end().ends_with("~=") => Context::Regex,
+      (Context::Quoted, '"') | (Context::Regex, '/') | (Context::Comment, '\n') => Context::Bare,
+      (context, _) => context,
+    };
+
+    substituted.push(c);
+    rest = &rest[c.len_utf8()..];
+  }
+
+  Ok(substituted)
+}
+
+fn expand_uses(source: &str, templates: &HashMap<&str, TemplateDefinition>, depth: usize) -> Result<String, ParseError> {
+  let mut expanded = String::new();
+
+  for line in source.split_inclusive('\n') {
+    let (name, arguments) = match template_use(line.trim()) {
+      Ok((_, use_)) => use_,
+      Err(_) => {
+        expanded.push_str(line);
+        continue;
+      }
+    };
+
+    let template = templates.get(name).ok_or_else(|| ParseError::UnknownTemplate(name.to_owned()))?;
+
+    if template.parameters.len() != arguments.len() {
+      return Err(ParseError::TemplateArity {
+        name: name.to_owned(),
+        expected: template.parameters.len(),
+        found: arguments.len(),
+      });
+    }
+
+    if depth >= MAX_TEMPLATE_DEPTH {
+      return Err(ParseError::Syntax(format!("Template `{}` is nested too deeply (recursive use?)", name)));
+    }
+
+    let arguments: HashMap<&str, &str> = template.parameters.iter().copied().zip(arguments).collect();
+    let body = substitute(template.body.trim(), &arguments)?;
+
+    expanded.push_str(&expand_uses(&body, templates, depth + 1)?);
+    expanded.push('\n');
+  }
+
+  Ok(expanded)
+}
+
+fn expand_templates(source: &str) -> Result<String, ParseError> {
+  let (source, templates) = collect_templates(source)?;
+
+  expand_uses(&source, &templates, 0)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+  Io(String),
+  Syntax(String),
+  UnknownTemplate(String),
+  TemplateArity { name: String, expected: usize, found: usize },
+  DuplicateId(String),
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(reason) => write!(f, "Failed to read rules: {}", reason),
+      Self::Syntax(reason) => write!(f, "Invalid rules: {}", reason),
+      Self::UnknownTemplate(name) => write!(f, "Unknown template `{}`", name),
+      Self::TemplateArity { name, expected, found } => write!(
+        f, "Template `{}` expects {} argument(s) but was given {}", name, expected, found
+      ),
+      Self::DuplicateId(id) => write!(f, "Rule id `{}` is used by more than one rule", id),
+    }
+  }
+}
+
+pub fn parse_source(source: &str) -> Result<Vec<Rule>, ParseError> {
+  let contents = expand_templates(source)?;
 
   let parser = all_consuming(tuple((many0_count(space_or_comment), rule_blocks, many0_count(space_or_comment))));
-  let (_, (_, rules, _)) = parser(&contents).unwrap();
+  let (_, (_, rules, _)) = parser(&contents).map_err(|e| {
+    let unparsed = match e {
+      nom::Err::Failure((rest, ErrorKind::Verify)) if looks_like_number(rest) => {
+        let number = rest.split_whitespace().next().unwrap_or(rest);
+
+        return ParseError::Syntax(format!("`{}` isn't a property; quote it to compare against the string, e.g. `\"{}\"`", number, number));
+      }
+      nom::Err::Error((rest, _)) | nom::Err::Failure((rest, _)) => rest,
+      nom::Err::Incomplete(_) => "",
+    };
+
+    ParseError::Syntax(format!("unexpected input at `{}`", unparsed.lines().next().unwrap_or("").trim()))
+  })?;
+
+  // Waivers, baselines and reports refer to rules by id, so ids must be unique
+  let mut ids = HashSet::new();
+
+  if let Some(rule) = rules.iter().find(|rule| !ids.insert(rule.id())) {
+    return Err(ParseError::DuplicateId(rule.id()));
+  }
 
-  Some(rules)
+  Ok(rules)
+}
+
+pub fn parse_rules(path: impl AsRef<Path>) -> Result<Vec<Rule>, ParseError> {
+  let contents = std::fs::read_to_string(path).map_err(|e| ParseError::Io(e.to_string()))?;
+
+  parse_source(&contents)
 }
 
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::rules::Severity;
 
   #[test]
   fn test_opening_brace() {
@@ -185,24 +710,286 @@ mod tests {
     );
   }
 
+  #[test]
+  fn test_equal_rule_references() {
+    assert_eq!(
+      equal_rule("= tags.region"),
+      Ok(("", Condition::EqualTemplate(Property::Custom("tags.region".to_owned()).into())))
+    );
+
+    assert_eq!(
+      equal_rule("= \"${tags.app}-web\""),
+      Ok(("", Condition::EqualTemplate("${tags.app}-web".try_into().unwrap())))
+    );
+  }
+
+  #[test]
+  fn test_match_rule_template() {
+    assert_eq!(
+      match_rule("~= /^${tags.app}-/"),
+      Ok(("", Condition::MatchTemplate("^${tags.app}-".try_into().unwrap())))
+    );
+  }
+
+  #[test]
+  fn test_equal_ignore_case_rule() {
+    assert_eq!(
+      equal_ignore_case_rule("=i \"UKSouth\""),
+      Ok(("", Condition::EqualIgnoreCase("UKSouth".to_owned())))
+    );
+  }
+
   #[test]
   fn test_match_rule() {
     assert_eq!(
       match_rule("~= /^[a-z]+$/"),
       Ok(("", Condition::Match(Regex::new("^[a-z]+$").unwrap())))
     );
+
+    assert!(match_rule("~= /^(st/").is_err());
+    assert!(match_rule("~= /^${tags.app/").is_err());
+    assert!(match_rule("~= /^(${tags.app}/").is_err());
+    assert!(parse_source("azure.* {\n  name ~= /^${tags.app/\n}\n").is_err());
+  }
+
+  #[test]
+  fn test_ordered_rule() {
+    assert_eq!(ordered_rule("<= 24"), Ok(("", Condition::LessOrEqual(24.0))));
+    assert_eq!(ordered_rule(">= 3"), Ok(("", Condition::GreaterOrEqual(3.0))));
+    assert_eq!(ordered_rule("< 2.5"), Ok(("", Condition::LessThan(2.5))));
+    assert_eq!(ordered_rule("> 0"), Ok(("", Condition::GreaterThan(0.0))));
+  }
+
+  #[test]
+  fn test_version_rule() {
+    assert_eq!(
+      rule_condition("version(properties.siteConfig.minTlsVersion) >= \"1.2\""),
+      Ok(("", (
+        Expression::Call(
+          Function::Version,
+          Box::new(Expression::Property("properties.siteConfig.minTlsVersion".try_into().unwrap()))
+        ),
+        Condition::VersionGreaterOrEqual(Version(vec![1, 2]))
+      )))
+    );
+
+    assert_eq!(version_rule("< \"1.28\""), Ok(("", Condition::VersionLessThan(Version(vec![1, 28])))));
+    assert!(version_rule("< \"latest\"").is_err());
+
+    // An unquoted bound is still a version, and only `version(...)` compares by version
+    assert_eq!(
+      rule_condition("version(properties.minTlsVersion) >= 1.2").map(|(_, (_, condition))| condition),
+      Ok(Condition::VersionGreaterOrEqual(Version(vec![1, 2])))
+    );
+    assert!(rule_condition("properties.minTlsVersion >= \"1.2\"").is_err());
+    assert!(rule_condition("version(properties.minTlsVersion) = \"1.2\"").is_err());
+    assert!(parse_source("azure.* {\n  properties.minTlsVersion >= \"1.2\"\n}\n").is_err());
+  }
+
+  #[test]
+  fn test_network_rule() {
+    assert_eq!(
+      rule_condition("properties.addressPrefix within \"10.0.0.0/8\""),
+      Ok(("", (
+        Expression::Property("properties.addressPrefix".try_into().unwrap()),
+        Condition::Within(Network::try_from("10.0.0.0/8").unwrap())
+      )))
+    );
+
+    assert_eq!(
+      network_rule("overlaps \"192.168.0.0/16\""),
+      Ok(("", Condition::Overlaps(Network::try_from("192.168.0.0/16").unwrap())))
+    );
+
+    assert!(network_rule("within \"10.0.0.0/40\"").is_err());
+  }
+
+  #[test]
+  fn test_equal_bool_rule() {
+    assert_eq!(
+      rule_condition("is_public_ip(properties.ipAddress) = false"),
+      Ok(("", (
+        Expression::Call(Function::IsPublicIp, Box::new(Expression::Property("properties.ipAddress".try_into().unwrap()))),
+        Condition::EqualBool(false)
+      )))
+    );
+
+    assert_eq!(
+      equal_rule("= trueRegion"),
+      Ok(("", Condition::EqualTemplate(Property::Custom("trueRegion".to_owned()).into())))
+    );
+    assert!(equal_bool_rule("= trueRegion").is_err());
+  }
+
+  #[test]
+  fn test_date_rule() {
+    assert_eq!(date_rule("older_than 90d"), Ok(("", Condition::OlderThan(Duration::days(90)))));
+    assert_eq!(date_rule("within 2w"), Ok(("", Condition::WithinDuration(Duration::weeks(2)))));
+    assert_eq!(date_rule("within 12h"), Ok(("", Condition::WithinDuration(Duration::hours(12)))));
+    assert!(date_rule("within \"10.0.0.0/8\"").is_err());
+
+    assert_eq!(
+      rule_condition("properties.attributes.expires not within 30d"),
+      Ok(("", (
+        Expression::Property("properties.attributes.expires".try_into().unwrap()),
+        Condition::Not(Box::new(Condition::WithinDuration(Duration::days(30))))
+      )))
+    );
+  }
+
+  #[test]
+  fn test_value() {
+    assert_eq!(value("\"UK South\""), Ok(("", "UK South")));
+    assert_eq!(value("\"*\""), Ok(("", "*")));
+  }
+
+  #[test]
+  fn test_expression() {
+    assert_eq!(
+      expression("properties.networkAcls.ipRules[*].value"),
+      Ok(("", Expression::Property("properties.networkAcls.ipRules[*].value".try_into().unwrap())))
+    );
+
+    assert_eq!(
+      expression("none(properties.securityRules[*].properties.sourceAddressPrefix)"),
+      Ok(("", Expression::Quantified(
+        Quantifier::None,
+        Box::new(Expression::Property("properties.securityRules[*].properties.sourceAddressPrefix".try_into().unwrap()))
+      )))
+    );
+
+    assert_eq!(
+      expression("len(name)"),
+      Ok(("", Expression::Call(Function::Length, Box::new(Expression::Property("name".try_into().unwrap())))))
+    );
+
+    assert_eq!(
+      expression("count(properties.networkAcls.ipRules)"),
+      Ok(("", Expression::Call(
+        Function::Count,
+        Box::new(Expression::Property("properties.networkAcls.ipRules".try_into().unwrap()))
+      )))
+    );
+
+    assert_eq!(
+      expression("normalize_location(location)"),
+      Ok(("", Expression::Call(Function::NormalizeLocation, Box::new(Expression::Property("location".try_into().unwrap())))))
+    );
+
+    assert_eq!(
+      expression("lower(trim(tags.env))"),
+      Ok(("", Expression::Call(
+        Function::Lower,
+        Box::new(Expression::Call(Function::Trim, Box::new(Expression::Property("tags.env".try_into().unwrap()))))
+      )))
+    );
+
+    assert_eq!(
+      expression("ref(properties.serverFarmId).location"),
+      Ok(("", Expression::Reference(
+        Box::new(Expression::Property("properties.serverFarmId".try_into().unwrap())),
+        "location".try_into().unwrap()
+      )))
+    );
+
+    assert_eq!(
+      expression("count(referenced_by(Microsoft.Network/privateEndpoints, properties.privateLinkServiceConnections[*].properties.privateLinkServiceId))"),
+      Ok(("", Expression::Call(
+        Function::Count,
+        Box::new(Expression::ReferencedBy(
+          "Microsoft.Network/privateEndpoints".to_owned(),
+          "properties.privateLinkServiceConnections[*].properties.privateLinkServiceId".try_into().unwrap()
+        ))
+      )))
+    );
+
+    assert_eq!(
+      expression("lengthy"),
+      Ok(("", Expression::Property("lengthy".try_into().unwrap())))
+    );
+
+    assert_eq!(
+      expression("any( tags[*] )"),
+      Ok(("", Expression::Quantified(Quantifier::Any, Box::new(Expression::Property("tags[*]".try_into().unwrap())))))
+    );
   }
 
   #[test]
   fn test_rule_condition() {
     assert_eq!(
       rule_condition("location = \"uksouth\""),
-      Ok(("", ("location".try_into().unwrap(), Condition::Equal("uksouth".to_owned()))))
+      Ok(("", (Expression::Property("location".try_into().unwrap()), Condition::Equal("uksouth".to_owned()))))
     );
 
     assert_eq!(
       rule_condition("name ~= /^[a-zA-Z0-9]+$/"),
-      Ok(("", ("name".try_into().unwrap(), Condition::Match(Regex::new("^[a-zA-Z0-9]+$").unwrap()))))
+      Ok(("", (Expression::Property("name".try_into().unwrap()), Condition::Match(Regex::new("^[a-zA-Z0-9]+$").unwrap()))))
+    );
+
+    assert_eq!(
+      rule_condition("all(properties.securityRules[*].properties.destinationPortRange) ~= /^[0-9]+$/"),
+      Ok(("", (
+        Expression::Quantified(
+          Quantifier::All,
+          Box::new(Expression::Property("properties.securityRules[*].properties.destinationPortRange".try_into().unwrap()))
+        ),
+        Condition::Match(Regex::new("^[0-9]+$").unwrap())
+      )))
+    );
+  }
+
+  #[test]
+  fn test_bare_numbers_are_rejected() {
+    assert!(rule_condition("properties.port = 22").is_err());
+    assert!(rule_condition("len(name) = 24").is_err());
+    assert!(rule_condition("name =i -1").is_err());
+    assert_eq!(
+      parse_source("azure.* {\n  properties.port = 22\n}\n"),
+      Err(ParseError::Syntax("`22` isn't a property; quote it to compare against the string, e.g. `\"22\"`".to_owned()))
+    );
+
+    // Quoted numbers, and properties that merely contain digits, are still fine
+    assert!(rule_condition("properties.port = \"22\"").is_ok());
+    assert!(rule_condition("name = tags.v2").is_ok());
+  }
+
+  #[test]
+  fn test_rule_condition_element() {
+    let property = |path: &str| Expression::Property(path.try_into().unwrap());
+
+    assert_eq!(
+      rule_condition("none(properties.securityRules[*]) { properties.sourceAddressPrefix = \"*\" and properties.destinationPortRange = \"22\" }"),
+      Ok(("", (
+        Expression::Quantified(Quantifier::None, Box::new(property("properties.securityRules[*]"))),
+        Condition::Element(vec![
+          (property("properties.sourceAddressPrefix"), Condition::Equal("*".to_owned())),
+          (property("properties.destinationPortRange"), Condition::Equal("22".to_owned())),
+        ])
+      )))
+    );
+
+    assert!(rule_condition("any(tags[*]) {}").is_err());
+    assert!(rule_condition("any(properties.rules[*]) { ref(id).location = location }").is_err());
+  }
+
+  #[test]
+  fn test_nested_quantifiers_are_rejected() {
+    assert!(rule_condition("any(all(properties.rules[*].ports[*])) = \"22\"").is_err());
+    assert!(rule_condition("len(any(properties.rules[*])) > 1").is_err());
+    assert!(rule_condition("ref(any(properties.ids[*])).location = \"uksouth\"").is_err());
+  }
+
+  #[test]
+  fn test_rule_condition_reference() {
+    assert_eq!(
+      rule_condition("ref(properties.serverFarmId).location = location"),
+      Ok(("", (
+        Expression::Reference(
+          Box::new(Expression::Property("properties.serverFarmId".try_into().unwrap())),
+          "location".try_into().unwrap()
+        ),
+        Condition::EqualTemplate(Property::Custom("location".to_owned()).into())
+      )))
     );
   }
 
@@ -212,8 +999,9 @@ mod tests {
       rule_block("azure.test-rg { location = \"uksouth\" }"),
       Ok(("", vec![Rule {
         selector: "azure.test-rg".try_into().unwrap(),
-        property: "location".try_into().unwrap(),
-        condition: Condition::Equal("uksouth".to_owned())
+        expression: Expression::Property("location".try_into().unwrap()),
+        condition: Condition::Equal("uksouth".to_owned()),
+        metadata: Metadata::default(),
       }]))
     );
 
@@ -221,8 +1009,9 @@ mod tests {
       rule_block("azure.test-rg {\n\tlocation = \"uksouth\"\n}"),
       Ok(("", vec![Rule {
         selector: "azure.test-rg".try_into().unwrap(),
-        property: "location".try_into().unwrap(),
-        condition: Condition::Equal("uksouth".to_owned())
+        expression: Expression::Property("location".try_into().unwrap()),
+        condition: Condition::Equal("uksouth".to_owned()),
+        metadata: Metadata::default(),
       }]))
     );
 
@@ -231,16 +1020,124 @@ mod tests {
       Ok(("", vec![
         Rule {
           selector: "azure.test-rg".try_into().unwrap(),
-          property: "location".try_into().unwrap(),
+          expression: Expression::Property("location".try_into().unwrap()),
           condition: Condition::Equal("uksouth".to_owned()),
+          metadata: Metadata::default(),
         },
         Rule {
           selector: "azure.test-rg".try_into().unwrap(),
-          property: "name".try_into().unwrap(),
+          expression: Expression::Property("name".try_into().unwrap()),
           condition: Condition::Match(Regex::new("^[a-zA-Z0-9]+$").unwrap()),
+          metadata: Metadata::default(),
         }
       ]))
     );
   }
 
+  #[test]
+  fn test_annotated_rule_block() {
+    assert_eq!(
+      rule_block("azure.test-rg {\n\t@id(\"rg-location\") @severity(\"critical\")\n\t@remediation(\"Redeploy to uksouth\")\n\tlocation = \"uksouth\"\n\t@id(\"rg-name\") @fixable name ~= /^rg-/\n}"),
+      Ok(("", vec![
+        Rule {
+          selector: "azure.test-rg".try_into().unwrap(),
+          expression: Expression::Property("location".try_into().unwrap()),
+          condition: Condition::Equal("uksouth".to_owned()),
+          metadata: Metadata {
+            id: Some("rg-location".to_owned()),
+            severity: Severity::Critical,
+            remediation: Some("Redeploy to uksouth".to_owned()),
+            fixable: false,
+          },
+        },
+        Rule {
+          selector: "azure.test-rg".try_into().unwrap(),
+          expression: Expression::Property("name".try_into().unwrap()),
+          condition: Condition::Match(Regex::new("^rg-").unwrap()),
+          metadata: Metadata {
+            id: Some("rg-name".to_owned()),
+            severity: Severity::Medium,
+            remediation: None,
+            fixable: true,
+          },
+        },
+      ]))
+    );
+
+    assert!(rule_block("azure.test-rg {\n\t@unknown(\"x\")\n\tlocation = \"uksouth\"\n}").is_err());
+    assert!(rule_block("azure.test-rg {\n\t@severity(\"urgent\")\n\tlocation = \"uksouth\"\n}").is_err());
+  }
+
+  #[test]
+  fn test_expand_templates() {
+    let source = "template naming(prefix, env) {\n  name ~= /^${prefix}-${env}-/\n  tags.env = \"${env}\"\n}\n\nazure.test-rg {\n  use naming(\"st\", \"prod\")\n  location = \"uksouth\"\n}\n";
+
+    assert_eq!(
+      parse_source(source),
+      Ok(vec![
+        Rule {
+          selector: "azure.test-rg".try_into().unwrap(),
+          expression: Expression::Property("name".try_into().unwrap()),
+          condition: Condition::Match(Regex::new("^st-prod-").unwrap()),
+          metadata: Metadata::default(),
+        },
+        Rule {
+          selector: "azure.test-rg".try_into().unwrap(),
+          expression: Expression::Property("tags.env".try_into().unwrap()),
+          condition: Condition::Equal("prod".to_owned()),
+          metadata: Metadata::default(),
+        },
+        Rule {
+          selector: "azure.test-rg".try_into().unwrap(),
+          expression: Expression::Property("location".try_into().unwrap()),
+          condition: Condition::Equal("uksouth".to_owned()),
+          metadata: Metadata::default(),
+        },
+      ])
+    );
+  }
+
+  #[test]
+  fn test_expand_templates_errors() {
+    let source = "template naming(prefix) {\n  name ~= /^${prefix}-/\n}\nazure.test-rg {\n  use naming(\"st\", \"prod\")\n}\n";
+    assert_eq!(
+      parse_source(source),
+      Err(ParseError::TemplateArity { name: "naming".to_owned(), expected: 1, found: 2 })
+    );
+
+    let source = "azure.test-rg {\n  use missing(\"st\")\n}\n";
+    assert_eq!(parse_source(source), Err(ParseError::UnknownTemplate("missing".to_owned())));
+
+    let source = "template naming(name) {\n  name ~= /^${name}-/\n}\n";
+    assert!(parse_source(source).is_err());
+
+    let source = "template tagged(env) {\n  tags.env = \"${env}\"\n}\nazure.test-rg {\n  use tagged(\"x${tags.app}\")\n}\n";
+    assert!(parse_source(source).is_err());
+  }
+
+  #[test]
+  fn test_expand_templates_escapes_arguments() {
+    let source = "template naming(prefix) {\n  name ~= /^${prefix}-${tags.app}/\n  // ${prefix}/\n  tags.prefix = \"${prefix}\"\n}\n\
+      azure.test-rg {\n  use naming(\"(\")\n  use naming(\"st.\")\n  use naming(\"a/b\")\n}\n";
+    let rules = parse_source(source).unwrap();
+    let conditions: Vec<String> = rules.iter().map(|rule| rule.condition.to_string()).collect();
+
+    assert_eq!(conditions, vec![
+      "~= /^\\(-${tags.app}/", "= \"(\"",
+      "~= /^st\\.-${tags.app}/", "= \"st.\"",
+      "~= /^a\\x2Fb-${tags.app}/", "= \"a/b\"",
+    ]);
+  }
+
+  #[test]
+  fn test_rule_ids() {
+    let source = "azure.* {\n  properties.minTlsVersion = \"1.2\"\n  properties.minTlsVersion = \"1.3\"\n}\n";
+    let ids: Vec<String> = parse_source(source).unwrap().iter().map(|rule| rule.id()).collect();
+
+    assert_eq!(ids, vec!["azure.*:properties.minTlsVersion = \"1.2\"", "azure.*:properties.minTlsVersion = \"1.3\""]);
+
+    let source = "azure.* {\n  @id(\"tls\") properties.minTlsVersion = \"1.2\"\n  @id(\"tls\") properties.minTlsVersion = \"1.3\"\n}\n";
+    assert_eq!(parse_source(source), Err(ParseError::DuplicateId("tls".to_owned())));
+  }
+
 }
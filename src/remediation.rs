@@ -0,0 +1,316 @@
+use crate::azurerm::{ Client, ClientError };
+use crate::engine::{ Evaluation, Outcome, ResourceCompliance };
+use crate::rules::{ Expression, Property, Rule };
+use serde_json::{ Map, Value };
+
+// A property change that makes a failing rule compliant, e.g. setting
+// `properties.httpsOnly` to `true` for `properties.httpsOnly = true`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+  pub resource_id: String,
+  pub path: String,
+  pub value: Value,
+}
+
+impl Change {
+  // Only rules comparing a single property (no functions, quantifiers or references) against
+  // exactly one value have a deterministic fix, and ARM won't update a resource's identity
+  // or location in place
+  pub fn for_rule(rule: &Rule, resource_id: &str) -> Option<Self> {
+    let path = match &rule.expression {
+      Expression::Property(Property::Custom(path)) if !path.contains('[') && !is_immutable(path) => path,
+      _ => return None,
+    };
+
+    Some(Change {
+      resource_id: resource_id.to_owned(),
+      path: path.to_owned(),
+      value: rule.condition.desired_value()?,
+    })
+  }
+
+  // e.g. `az resource update --ids '/subscriptions/...' --set 'properties.httpsOnly=true'`
+  pub fn az_command(&self) -> String {
+    format!(
+      "az resource update --ids {} --set {}",
+      shell_quote(&self.resource_id),
+      shell_quote(&format!("{}={}", self.path, self.value))
+    )
+  }
+
+  // The body of an ARM PATCH request, e.g. `{"properties":{"httpsOnly":true}}`
+  pub fn patch_body(&self) -> Value {
+    self.path.rsplit('.').fold(self.value.clone(), |value, key| {
+      let mut object = Map::new();
+      object.insert(key.to_owned(), value);
+      Value::Object(object)
+    })
+  }
+}
+
+// How to fix a violation: a generated change where there is one, and the rule's
+// `@remediation` guidance where given
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remediation {
+  pub change: Option<Change>,
+  pub guidance: Option<String>,
+}
+
+impl Remediation {
+  pub fn for_evaluation(evaluation: &Evaluation, resource_id: &str) -> Option<Self> {
+    match evaluation.outcome {
+      Outcome::NonCompliant { .. } | Outcome::Baselined { .. } => {}
+      _ => return None,
+    }
+
+    let change = Change::for_rule(&evaluation.rule, resource_id);
+    let guidance = evaluation.rule.metadata.remediation.clone();
+
+    if change.is_none() && guidance.is_none() { None } else { Some(Remediation { change, guidance }) }
+  }
+}
+
+const IMMUTABLE: &[&str] = &["location", "name", "type", "id", "kind"];
+
+fn is_immutable(path: &str) -> bool {
+  IMMUTABLE.iter().any(|property| property.eq_ignore_ascii_case(path))
+}
+
+fn shell_quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// A shell script of `az` commands fixing every (new or baselined) violation that has a generated change
+pub fn script(results: &[ResourceCompliance]) -> String {
+  let mut script = String::from("#!/bin/sh\n# Generated by cloud-lint. Review each change before running.\nset -e\n");
+
+  for resource in results {
+    for evaluation in &resource.evaluations {
+      let remediation = match Remediation::for_evaluation(evaluation, &resource.resource_id) {
+        Some(remediation) => remediation,
+        None => continue,
+      };
+
+      script.push_str(&format!("\n# {}: {} ({})\n", resource.resource_name, evaluation.rule, evaluation.rule.id()));
+
+      // Guidance may span lines; keep every line of it commented out
+      if let Some(guidance) = &remediation.guidance {
+        script.push_str(&format!("# {}\n", guidance.replace(['\n', '\r'], " ")));
+      }
+
+      match &remediation.change {
+        Some(change) => script.push_str(&format!("{}\n", change.az_command())),
+        None => script.push_str("# No automatic fix; apply the guidance above by hand\n"),
+      }
+    }
+  }
+
+  script
+}
+
+// The changes `--fix` would make: one per violation of an `@fixable` rule with a generated change
+pub fn fixes(results: &[ResourceCompliance]) -> Vec<Change> {
+  results.iter()
+    .flat_map(|resource| {
+      resource.evaluations.iter()
+        .filter(|evaluation| evaluation.rule.metadata.fixable)
+        .filter_map(move |evaluation| Remediation::for_evaluation(evaluation, &resource.resource_id)?.change)
+    })
+    .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct FixSummary {
+  pub applied: Vec<Change>,
+  pub failed: Vec<(Change, ClientError)>,
+}
+
+// PATCHes each change in turn, carrying on past failures
+pub fn apply(client: &Client, changes: Vec<Change>) -> FixSummary {
+  let mut summary = FixSummary::default();
+
+  for change in changes {
+    match client.patch_resource(&change.resource_id, &change.patch_body()) {
+      Ok(_) => {
+        log::info!(resource = change.resource_id.as_str(), path = change.path.as_str(); "applied fix");
+        summary.applied.push(change);
+      }
+      Err(e) => {
+        log::warn!(resource = change.resource_id.as_str(), path = change.path.as_str(), error:% = e; "fix failed");
+        summary.failed.push((change, e));
+      }
+    }
+  }
+
+  summary
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use chrono::{ TimeZone, Utc };
+  use serde_json::json;
+  use std::convert::TryFrom;
+  use std::io::{ BufRead, BufReader, Read, Write };
+  use std::net::TcpListener;
+  use std::sync::{ Arc, Mutex };
+  use std::thread;
+
+  type Requests = Arc<Mutex<Vec<(String, String, String)>>>;
+
+  // A minimal stand-in for ARM: answers provider lookups, records every request, and
+  // rejects PATCHes to resources whose id contains `conflict`
+  fn mock_arm() -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests: Requests = Arc::default();
+    let recorded = requests.clone();
+
+    thread::spawn(move || {
+      for stream in listener.incoming() {
+        let mut stream = stream.unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap().to_owned(), parts.next().unwrap().to_owned());
+
+        let mut length = 0;
+        loop {
+          let mut header = String::new();
+          reader.read_line(&mut header).unwrap();
+          if header.trim().is_empty() {
+            break;
+          }
+          if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+            length = value.trim().parse().unwrap();
+          }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+
+        let (status, response) = if method == "GET" {
+          ("200 OK", json!({
+            "namespace": "Microsoft.Web",
+            "resourceTypes": [{ "resourceType": "sites", "apiVersions": ["2023-12-01-preview", "2022-09-01"] }],
+          }).to_string())
+        } else if path.contains("conflict") {
+          ("409 Conflict", json!({ "error": { "code": "Conflict" } }).to_string())
+        } else {
+          ("200 OK", "{}".to_owned())
+        };
+
+        recorded.lock().unwrap().push((method, path, String::from_utf8(body).unwrap()));
+        write!(
+          stream,
+          "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+          status, response.len(), response
+        ).unwrap();
+      }
+    });
+
+    (url, requests)
+  }
+
+  #[test]
+  fn test_change() {
+    let rules = parse_source(
+      "azure.* {\n  properties.siteConfig.minTlsVersion = \"1.2\"\n  properties.httpsOnly = true\n  len(name) < 24\n  tags[*] = \"x\"\n}\n"
+    ).unwrap();
+    let id = "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/it's-app";
+
+    let change = Change::for_rule(&rules[0], id).unwrap();
+    assert_eq!(change.patch_body(), json!({ "properties": { "siteConfig": { "minTlsVersion": "1.2" } } }));
+    assert_eq!(
+      change.az_command(),
+      "az resource update --ids '/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/it'\\''s-app' \
+        --set 'properties.siteConfig.minTlsVersion=\"1.2\"'"
+    );
+
+    assert_eq!(Change::for_rule(&rules[1], id).unwrap().patch_body(), json!({ "properties": { "httpsOnly": true } }));
+    assert_eq!(Change::for_rule(&rules[2], id), None);
+    assert_eq!(Change::for_rule(&rules[3], id), None);
+
+    // Properties ARM can't change in place are left to the guidance
+    let rules = parse_source("azure.* {\n  location = \"uksouth\"\n  name = \"app\"\n  kind = \"app\"\n  id = \"x\"\n}\n").unwrap();
+    assert!(rules.iter().all(|rule| Change::for_rule(rule, id).is_none()));
+  }
+
+  #[test]
+  fn test_script() {
+    let resource = Resource::try_from(json!({
+      "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app",
+      "location": "westeurope",
+      "properties": { "httpsOnly": false },
+    })).unwrap();
+    let rules = parse_source(
+      "azure.* {\n  @remediation(\"Enable HTTPS Only under TLS/SSL settings\")\n  properties.httpsOnly = true\n  \
+        @remediation(\"Redeploy the app to uksouth\")\n  location ~= /^uk/\n  location = \"westeurope\"\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).evaluate(std::slice::from_ref(&resource), &rules);
+
+    assert_eq!(script(&results), "#!/bin/sh\n# Generated by cloud-lint. Review each change before running.\nset -e\n\
+      \n# app: properties.httpsOnly = true (azure.*:properties.httpsOnly = true)\n\
+      # Enable HTTPS Only under TLS/SSL settings\n\
+      az resource update --ids '/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app' --set 'properties.httpsOnly=true'\n\
+      \n# app: location ~= /^uk/ (azure.*:location ~= /^uk/)\n\
+      # Redeploy the app to uksouth\n\
+      # No automatic fix; apply the guidance above by hand\n");
+  }
+
+  #[test]
+  fn test_script_comments_out_multiline_guidance() {
+    let resource = Resource::try_from(json!({
+      "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app",
+      "location": "westeurope",
+    })).unwrap();
+    let rules = parse_source("azure.* {\n  @remediation(\"Move it\nrm -rf ~\r\nthen redeploy\") location ~= /^uk/\n}\n").unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).evaluate(std::slice::from_ref(&resource), &rules);
+
+    let script = script(&results);
+    assert!(script.contains("# Move it rm -rf ~  then redeploy\n"));
+    assert!(script.lines().all(|line| line.is_empty() || line.starts_with('#') || line == "set -e"));
+  }
+
+  #[test]
+  fn test_fix_against_mock_arm() {
+    let resources: Vec<Resource> = ["app", "conflict-app"].iter()
+      .map(|name| Resource::try_from(json!({
+        "id": format!("/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/{}", name),
+        "location": "westeurope",
+        "properties": { "httpsOnly": false },
+      })).unwrap())
+      .collect();
+    let rules = parse_source(
+      "azure.* {\n  @fixable properties.httpsOnly = true\n  location = \"uksouth\"\n  @fixable location = \"ukwest\"\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).evaluate(&resources, &rules);
+
+    // One location rule isn't `@fixable`, and the other can't be fixed in place
+    let changes = fixes(&results);
+    assert_eq!(changes.iter().map(|c| c.path.as_str()).collect::<Vec<_>>(), vec!["properties.httpsOnly"; 2]);
+
+    let (url, requests) = mock_arm();
+    let summary = apply(&Client::with_token(&url, "token"), changes);
+
+    assert_eq!(summary.applied.len(), 1);
+    assert_eq!(summary.failed.len(), 1);
+    assert!(matches!(summary.failed[0].1, ClientError::Status(409, _)));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].0, "GET");
+    assert_eq!(requests[0].1, "/subscriptions/sub-a/providers/Microsoft.Web?api-version=2021-04-01");
+    assert_eq!(requests[1], (
+      "PATCH".to_owned(),
+      "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app?api-version=2022-09-01".to_owned(),
+      r#"{"properties":{"httpsOnly":true}}"#.to_owned(),
+    ));
+  }
+}
@@ -0,0 +1,212 @@
+use super::xml_escape as escape;
+use crate::engine::{ Evaluation, Outcome, ResourceCompliance, ResourceGroupCompliance };
+use crate::remediation::Remediation;
+use crate::scoring::Breakdown;
+use chrono::{ DateTime, SecondsFormat, Utc };
+use std::collections::BTreeSet;
+
+// A single HTML page with its styles and script inline, so it works offline and as an
+// email attachment. The summary and score breakdowns come first, then a collapsible
+// section per resource listing its evaluations. The filters hide evaluation rows (and
+// resources left with none) by subscription, group, kind, rule, severity or outcome.
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+h1 .score { font-size: 2em; }
+table { border-collapse: collapse; margin: 0.5em 0 1em; }
+th, td { border: 1px solid #ddd; padding: 0.25em 0.6em; text-align: left; vertical-align: top; }
+details.resource { border: 1px solid #ccc; border-radius: 4px; margin: 0.5em 0; padding: 0.4em 0.8em; }
+summary { cursor: pointer; }
+.filters label { margin-right: 1em; }
+.compliant { color: #1a7f37; }
+.noncompliant, .error { color: #cf222e; }
+.waived, .baselined { color: #9a6700; }
+code { white-space: pre-wrap; }
+";
+
+const SCRIPT: &str = "
+const filters = document.querySelectorAll('select[data-filter]');
+function applyFilters() {
+  const active = [...filters].filter(f => f.value).map(f => [f.dataset.filter, f.value]);
+  document.querySelectorAll('details.resource').forEach(resource => {
+    let visible = 0;
+    resource.querySelectorAll('tr.evaluation').forEach(row => {
+      const shown = active.every(([key, value]) => (row.dataset[key] ?? resource.dataset[key]) === value);
+      row.hidden = !shown;
+      if (shown) visible++;
+    });
+    resource.hidden = visible === 0;
+  });
+}
+filters.forEach(f => f.addEventListener('change', applyFilters));
+";
+
+fn portal_link(resource_id: &str) -> String {
+  format!("https://portal.azure.com/#@/resource{}", resource_id)
+}
+
+fn filter(key: &str, label: &str, values: &BTreeSet<String>) -> String {
+  let options: String = values.iter()
+    .map(|value| format!("<option>{}</option>", escape(value)))
+    .collect();
+
+  format!(
+    "<label>{} <select data-filter=\"{}\"><option value=\"\">All</option>{}</select></label>\n",
+    label, key, options
+  )
+}
+
+fn evaluation_row(evaluation: &Evaluation, resource_id: &str) -> String {
+  let rule = &evaluation.rule;
+  let outcome = evaluation.outcome.label();
+
+  let mut notes = match &evaluation.outcome {
+    Outcome::Waived { waiver, .. } => format!("Waived until {}: {}", waiver.expires, escape(&waiver.justification)),
+    Outcome::Error { reason } => escape(reason),
+    _ => String::new(),
+  };
+
+  if let Some(remediation) = Remediation::for_evaluation(evaluation, resource_id) {
+    if let Some(guidance) = &remediation.guidance {
+      notes.push_str(&escape(guidance));
+    }
+
+    if let Some(change) = &remediation.change {
+      if !notes.is_empty() {
+        notes.push_str("<br>");
+      }
+      notes.push_str(&format!("<code>{}</code>", escape(&change.az_command())));
+    }
+  }
+
+  format!(
+    "<tr class=\"evaluation\" data-rule=\"{id}\" data-severity=\"{severity}\" data-outcome=\"{outcome}\">\
+      <td>{id}</td><td>{severity}</td><td class=\"{outcome}\">{outcome}</td>\
+      <td><code>{expected}</code></td><td><code>{actual}</code></td><td>{notes}</td></tr>\n",
+    id = escape(&rule.id()),
+    severity = rule.metadata.severity,
+    outcome = outcome,
+    expected = escape(&rule.to_string()),
+    actual = escape(&evaluation.found()),
+    notes = notes,
+  )
+}
+
+fn resource_section(resource: &ResourceCompliance) -> String {
+  let violations = resource.count(|o| matches!(o, Outcome::NonCompliant { .. } | Outcome::Baselined { .. }));
+  let rows: String = resource.evaluations.iter().map(|e| evaluation_row(e, &resource.resource_id)).collect();
+
+  format!(
+    "<details class=\"resource\" data-subscription=\"{subscription}\" data-group=\"{group}\" data-kind=\"{kind}\">\n\
+      <summary><strong>{name}</strong> ({kind}, {group}): {violations} of {total} rules violated</summary>\n\
+      <p><a href=\"{link}\">{id}</a></p>\n\
+      <table>\n<tr><th>Rule</th><th>Severity</th><th>Outcome</th><th>Expected</th><th>Actual</th><th>Notes</th></tr>\n\
+      {rows}</table>\n</details>\n",
+    subscription = escape(&resource.subscription_id),
+    group = escape(&resource.resource_group),
+    kind = escape(&resource.resource_type),
+    name = escape(&resource.resource_name),
+    violations = violations,
+    total = resource.evaluations.len(),
+    link = escape(&portal_link(&resource.resource_id)),
+    id = escape(&resource.resource_id),
+    rows = rows,
+  )
+}
+
+pub fn render(results: &[ResourceCompliance], generated: DateTime<Utc>) -> String {
+  let group = ResourceGroupCompliance::from_results(results);
+  let breakdown = Breakdown::from_results(results);
+
+  let mut breakdowns = String::new();
+  for (heading, scores) in breakdown.sections() {
+    breakdowns.push_str(&format!("<h3>By {}</h3>\n<table>\n<tr><th>{}</th><th>Score</th><th>Compliant</th><th>Violations</th><th>Waived</th><th>Errors</th></tr>\n", heading, heading));
+
+    for (key, score) in scores {
+      breakdowns.push_str(&format!(
+        "<tr><td>{}</td><td>{:.0}%</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+        escape(key), score.percentage(), score.compliant, score.noncompliant + score.baselined, score.waived, score.errors
+      ));
+    }
+
+    breakdowns.push_str("</table>\n");
+  }
+
+  let values = |f: &dyn Fn(&ResourceCompliance, &Evaluation) -> String| -> BTreeSet<String> {
+    results.iter().flat_map(|r| r.evaluations.iter().map(move |e| f(r, e))).collect()
+  };
+  let filters = [
+    filter("subscription", "Subscription", &values(&|r, _| r.subscription_id.clone())),
+    filter("group", "Group", &values(&|r, _| r.resource_group.clone())),
+    filter("kind", "Kind", &values(&|r, _| r.resource_type.clone())),
+    filter("rule", "Rule", &values(&|_, e| e.rule.id())),
+    filter("severity", "Severity", &values(&|_, e| e.rule.metadata.severity.to_string())),
+    filter("outcome", "Outcome", &values(&|_, e| e.outcome.label().to_owned())),
+  ].concat();
+
+  let resources: String = results.iter().map(resource_section).collect();
+
+  format!(
+    "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>cloud-lint compliance report</title>\n\
+      <style>{style}</style>\n</head>\n<body>\n\
+      <h1>Compliance score <span class=\"score\">{score:.0}%</span></h1>\n\
+      <p>Generated {generated}. {resources_count} resources, {compliant} compliant evaluations, {noncompliant} new violations, \
+      {baselined} baselined, {waived} waived, {errors} errors.</p>\n\
+      <h2>Breakdown</h2>\n{breakdowns}\
+      <h2>Resources</h2>\n<div class=\"filters\">\n{filters}</div>\n{resources}\
+      <script>{script}</script>\n</body>\n</html>\n",
+    style = STYLE,
+    score = breakdown.overall.percentage(),
+    generated = generated.to_rfc3339_opts(SecondsFormat::Secs, true),
+    resources_count = group.resource_count,
+    compliant = group.compliant_rule_evaluations,
+    noncompliant = group.noncompliant_rule_evaluations,
+    baselined = group.baselined_rule_evaluations,
+    waived = group.waived_rule_evaluations,
+    errors = group.error_rule_evaluations,
+    breakdowns = breakdowns,
+    filters = filters,
+    resources = resources,
+    script = SCRIPT,
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use chrono::TimeZone;
+  use serde_json::json;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_render() {
+    let resources: Vec<Resource> = vec![
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app", "location": "westeurope" }),
+    ].into_iter().map(|r| Resource::try_from(r).unwrap()).collect();
+    let rules = parse_source(
+      "azure.* {\n  @id(\"location\") @severity(\"high\") @remediation(\"Redeploy to <uksouth>\")\n  location = \"uksouth\"\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    let html = render(&Engine::new().at(now).evaluate(&resources, &rules), now);
+
+    assert!(html.contains("<h1>Compliance score <span class=\"score\">0%</span></h1>"));
+    assert!(html.contains("<p>Generated 2026-10-18T12:00:00Z. 1 resources,"));
+    assert!(html.contains("<select data-filter=\"severity\"><option value=\"\">All</option><option>high</option></select>"));
+    assert!(html.contains(
+      "<details class=\"resource\" data-subscription=\"sub-a\" data-group=\"web-rg\" data-kind=\"app_service\">"
+    ));
+    assert!(html.contains(
+      "<a href=\"https://portal.azure.com/#@/resource/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app\">"
+    ));
+    assert!(html.contains(
+      "<tr class=\"evaluation\" data-rule=\"location\" data-severity=\"high\" data-outcome=\"noncompliant\">\
+        <td>location</td><td>high</td><td class=\"noncompliant\">noncompliant</td>\
+        <td><code>location = &quot;uksouth&quot;</code></td><td><code>&quot;westeurope&quot;</code></td>\
+        <td>Redeploy to &lt;uksouth&gt;</td></tr>"
+    ));
+  }
+}
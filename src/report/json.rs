@@ -0,0 +1,194 @@
+use crate::baseline::Baseline;
+use crate::engine::{ Evaluation, Outcome, ResourceCompliance, ResourceGroupCompliance };
+use crate::remediation::Remediation;
+use crate::rules::Rule;
+use crate::scoring::{ Breakdown, Score };
+use chrono::{ DateTime, SecondsFormat, Utc };
+use serde_json::{ json, Map, Value };
+use std::collections::BTreeMap;
+
+// Schema version 1. Fields are only ever added within a version; renaming or removing one
+// bumps `version`.
+//
+// {
+//   "version": 1,
+//   "generated": "2026-10-18T12:00:00Z",
+//   "summary": {
+//     "score": 95.5,                      severity-weighted percentage, see `Score::percentage`
+//     "resources": 2, "compliant_resources": 1, "noncompliant_resources": 1, "errored_resources": 0,
+//     "evaluations": 4, "compliant": 3, "noncompliant": 1, "waived": 0, "baselined": 0, "errors": 0,
+//     "highest_severity": "low"           of any new violation, or null
+//   },
+//   "scores": {                           each keyed by subscription id, group, kind or rule id
+//     "subscriptions": { "sub-a": { "score": 95.5, "compliant": 3, "noncompliant": 1, "waived": 0, "baselined": 0, "errors": 0 } },
+//     "groups": { .. }, "kinds": { .. }, "rules": { .. }
+//   },
+//   "resources": [{
+//     "id": "/subscriptions/.../sites/app", "subscription": "sub-a", "group": "web-rg", "name": "app", "kind": "app_service",
+//     "evaluations": [{
+//       "rule": {
+//         "id": "location", "selector": "azure.*", "condition": "location = \"uksouth\"",
+//         "severity": "medium", "remediation": "Redeploy to uksouth", "fixable": false
+//       },
+//       "outcome": "noncompliant",        compliant, noncompliant, waived, baselined or error
+//       "actual": "westeurope",           the observed value, or null if missing
+//       "reason": "..",                   errors only
+//       "waiver": { "justification": "..", "expires": "2027-01-01" },   waived only
+//       "fix": { "az": "az resource update ..", "patch": { "properties": { "httpsOnly": true } } }   violations with a generated change only
+//     }],
+//     "expired_waivers": [{ "rule": "location", "justification": "..", "expires": "2026-01-01" }]
+//   }],
+//   "violations": [{ "rule": "location", "resource": "/subscriptions/.../sites/app" }]
+// }
+//
+// `violations` lists new and baselined violations in the baseline file format, so a JSON
+// report can be passed straight to `--baseline`.
+
+pub const VERSION: u32 = 1;
+
+fn score(score: &Score) -> Value {
+  json!({
+    "score": score.percentage(),
+    "compliant": score.compliant,
+    "noncompliant": score.noncompliant,
+    "waived": score.waived,
+    "baselined": score.baselined,
+    "errors": score.errors,
+  })
+}
+
+fn scores(scores: &BTreeMap<String, Score>) -> Value {
+  Value::Object(scores.iter().map(|(key, s)| (key.clone(), score(s))).collect::<Map<_, _>>())
+}
+
+fn rule(rule: &Rule) -> Value {
+  json!({
+    "id": rule.id(),
+    "selector": rule.selector.to_string(),
+    "condition": rule.to_string(),
+    "severity": rule.metadata.severity.to_string(),
+    "remediation": rule.metadata.remediation,
+    "fixable": rule.metadata.fixable,
+  })
+}
+
+fn evaluation(evaluation: &Evaluation, resource_id: &str) -> Value {
+  let mut value = json!({
+    "rule": rule(&evaluation.rule),
+    "outcome": evaluation.outcome.label(),
+    "actual": evaluation.actual,
+  });
+
+  match &evaluation.outcome {
+    Outcome::Error { reason } => value["reason"] = json!(reason),
+    Outcome::Waived { waiver, .. } => {
+      value["waiver"] = json!({ "justification": waiver.justification, "expires": waiver.expires.to_string() });
+    }
+    _ => {}
+  }
+
+  if let Some(change) = Remediation::for_evaluation(evaluation, resource_id).and_then(|r| r.change) {
+    value["fix"] = json!({ "az": change.az_command(), "patch": change.patch_body() });
+  }
+
+  value
+}
+
+fn resource(resource: &ResourceCompliance) -> Value {
+  json!({
+    "id": resource.resource_id,
+    "subscription": resource.subscription_id,
+    "group": resource.resource_group,
+    "name": resource.resource_name,
+    "kind": resource.resource_type,
+    "evaluations": resource.evaluations.iter().map(|e| evaluation(e, &resource.resource_id)).collect::<Vec<_>>(),
+    "expired_waivers": resource.expired_waivers.iter()
+      .map(|(rule, waiver)| json!({ "rule": rule.id(), "justification": waiver.justification, "expires": waiver.expires.to_string() }))
+      .collect::<Vec<_>>(),
+  })
+}
+
+pub fn to_json(results: &[ResourceCompliance], generated: DateTime<Utc>) -> Value {
+  let group = ResourceGroupCompliance::from_results(results);
+  let breakdown = Breakdown::from_results(results);
+
+  json!({
+    "version": VERSION,
+    "generated": generated.to_rfc3339_opts(SecondsFormat::Secs, true),
+    "summary": {
+      "score": breakdown.overall.percentage(),
+      "resources": group.resource_count,
+      "compliant_resources": group.compliant_resources,
+      "noncompliant_resources": group.noncompliant_resources,
+      "errored_resources": group.errored_resources,
+      "evaluations": group.evaluated_rules,
+      "compliant": group.compliant_rule_evaluations,
+      "noncompliant": group.noncompliant_rule_evaluations,
+      "waived": group.waived_rule_evaluations,
+      "baselined": group.baselined_rule_evaluations,
+      "errors": group.error_rule_evaluations,
+      "highest_severity": group.highest_severity.map(|severity| severity.to_string()),
+    },
+    "scores": {
+      "subscriptions": scores(&breakdown.subscriptions),
+      "groups": scores(&breakdown.groups),
+      "kinds": scores(&breakdown.kinds),
+      "rules": scores(&breakdown.rules),
+    },
+    "resources": results.iter().map(resource).collect::<Vec<_>>(),
+    "violations": Baseline::from_results(results).to_json()["violations"],
+  })
+}
+
+pub fn render(results: &[ResourceCompliance], generated: DateTime<Utc>) -> String {
+  serde_json::to_string_pretty(&to_json(results, generated)).unwrap_or_default() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use chrono::TimeZone;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_to_json() {
+    let resources: Vec<Resource> = vec![
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app", "location": "westeurope" }),
+    ].into_iter().map(|r| Resource::try_from(r).unwrap()).collect();
+    let rules = parse_source(
+      "azure.* {\n  @id(\"location\") @remediation(\"Redeploy to uksouth\")\n  location = \"uksouth\"\n  \
+        @id(\"https\") properties.httpsOnly = true\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).evaluate(&resources, &rules);
+
+    let report = to_json(&results, now);
+
+    assert_eq!(report["version"], 1);
+    assert_eq!(report["generated"], "2026-10-18T12:00:00Z");
+    assert_eq!(report["summary"]["noncompliant"], 1);
+    assert_eq!(report["summary"]["errors"], 1);
+    assert_eq!(report["summary"]["highest_severity"], "medium");
+    assert_eq!(report["scores"]["rules"]["location"], json!({
+      "score": 0.0, "compliant": 0, "noncompliant": 1, "waived": 0, "baselined": 0, "errors": 0,
+    }));
+
+    let evaluations = &report["resources"][0]["evaluations"];
+    assert_eq!(evaluations[0], json!({
+      "rule": {
+        "id": "location", "selector": "azure.*", "condition": "location = \"uksouth\"",
+        "severity": "medium", "remediation": "Redeploy to uksouth", "fixable": false,
+      },
+      "outcome": "noncompliant",
+      "actual": "westeurope",
+    }));
+    assert_eq!(evaluations[1]["outcome"], "error");
+    assert_eq!(evaluations[1]["actual"], Value::Null);
+
+    // Reports double as baselines
+    assert_eq!(Baseline::try_from(&report).unwrap(), Baseline::from_results(&results));
+  }
+}
@@ -0,0 +1,192 @@
+use super::xml_escape;
+use crate::engine::{ Evaluation, Outcome, ResourceCompliance };
+use crate::scoring::{ Breakdown, Score };
+use std::collections::BTreeMap;
+
+// <testsuites name="cloud-lint" tests=".." failures=".." errors=".." skipped="..">
+//   <testsuite name="web-rg" ...>                 one per resource group
+//     <properties><property name="score" value="95.5"/></properties>
+//     <testcase classname="app_service.legacy-app" name="app-location">
+//       <failure type="noncompliant" message="expected location = &quot;uksouth&quot;, found &quot;westeurope&quot;">..</failure>
+//     </testcase>
+//
+// Waived, baselined and not applicable evaluations are skipped cases; errors are `<error>`s.
+
+#[derive(Default)]
+struct Counts {
+  tests: usize,
+  failures: usize,
+  errors: usize,
+  skipped: usize,
+}
+
+impl Counts {
+  fn attributes(&self) -> String {
+    format!(r#"tests="{}" failures="{}" errors="{}" skipped="{}""#, self.tests, self.failures, self.errors, self.skipped)
+  }
+}
+
+fn evaluation_case(classname: &str, evaluation: &Evaluation, counts: &mut Counts) -> String {
+  let rule = &evaluation.rule;
+  let failure = format!("expected {}, found {}", rule, evaluation.found());
+  let details = format!(
+    "rule: {}\nseverity: {}\nexpected: {}\nactual: {}",
+    rule.id(), rule.metadata.severity, rule, evaluation.found()
+  );
+
+  counts.tests += 1;
+
+  let body = match &evaluation.outcome {
+    Outcome::Compliant => String::new(),
+    Outcome::NonCompliant { .. } => {
+      counts.failures += 1;
+      format!(
+        "\n      <failure type=\"noncompliant\" message=\"{}\">{}</failure>\n    ",
+        xml_escape(&failure), xml_escape(&details)
+      )
+    }
+    Outcome::Waived { waiver, .. } => {
+      counts.skipped += 1;
+      let message = format!("{} (waived until {}: {})", failure, waiver.expires, waiver.justification);
+      format!("\n      <skipped message=\"{}\"/>\n    ", xml_escape(&message))
+    }
+    Outcome::Baselined { .. } => {
+      counts.skipped += 1;
+      format!("\n      <skipped message=\"{}\"/>\n    ", xml_escape(&format!("{} (baselined)", failure)))
+    }
+    Outcome::NotApplicable => {
+      counts.skipped += 1;
+      let message = format!("{} doesn't apply to this resource", rule.selector);
+      format!("\n      <skipped message=\"{}\"/>\n    ", xml_escape(&message))
+    }
+    Outcome::Error { reason } => {
+      counts.errors += 1;
+      format!(
+        "\n      <error type=\"error\" message=\"{}\">{}</error>\n    ",
+        xml_escape(reason), xml_escape(&details)
+      )
+    }
+  };
+
+  format!(
+    "    <testcase classname=\"{}\" name=\"{}\">{}</testcase>\n",
+    xml_escape(classname), xml_escape(&rule.id()), body
+  )
+}
+
+pub fn render(results: &[ResourceCompliance]) -> String {
+  let breakdown = Breakdown::from_results(results);
+  let mut groups: BTreeMap<&str, Vec<&ResourceCompliance>> = BTreeMap::new();
+
+  for resource in results {
+    groups.entry(&resource.resource_group).or_default().push(resource);
+  }
+
+  let mut totals = Counts::default();
+  let mut suites = String::new();
+
+  for (group, resources) in groups {
+    let mut counts = Counts::default();
+    let mut cases = String::new();
+
+    for resource in resources {
+      let classname = format!("{}.{}", resource.resource_type, resource.resource_name);
+
+      for evaluation in &resource.evaluations {
+        cases.push_str(&evaluation_case(&classname, evaluation, &mut counts));
+      }
+    }
+
+    let score = breakdown.groups.get(group).map(Score::percentage).unwrap_or(100.0);
+
+    suites.push_str(&format!(
+      "  <testsuite name=\"{}\" {}>\n    <properties>\n      <property name=\"score\" value=\"{:.1}\"/>\n    </properties>\n{}  </testsuite>\n",
+      xml_escape(group), counts.attributes(), score, cases
+    ));
+
+    totals.tests += counts.tests;
+    totals.failures += counts.failures;
+    totals.errors += counts.errors;
+    totals.skipped += counts.skipped;
+  }
+
+  format!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"cloud-lint\" {}>\n{}</testsuites>\n",
+    totals.attributes(), suites
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use chrono::{ TimeZone, Utc };
+  use serde_json::json;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_render() {
+    let resources: Vec<Resource> = vec![
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app", "location": "westeurope" }),
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/data-rg/providers/Microsoft.Storage/storageAccounts/store", "location": "uksouth" }),
+    ].into_iter().map(|r| Resource::try_from(r).unwrap()).collect();
+    let rules = parse_source(
+      "azure.* {\n  @id(\"location\") location = \"uksouth\"\n}\nazure.data-rg {\n  @id(\"data-location\") location = \"uksouth\"\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    let xml = render(&Engine::new().at(now).evaluate(&resources, &rules));
+
+    assert_eq!(xml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+      <testsuites name=\"cloud-lint\" tests=\"4\" failures=\"1\" errors=\"0\" skipped=\"1\">\n  \
+      <testsuite name=\"data-rg\" tests=\"2\" failures=\"0\" errors=\"0\" skipped=\"0\">\n    \
+      <properties>\n      <property name=\"score\" value=\"100.0\"/>\n    </properties>\n    \
+      <testcase classname=\"Microsoft.Storage/storageAccounts.store\" name=\"location\"></testcase>\n    \
+      <testcase classname=\"Microsoft.Storage/storageAccounts.store\" name=\"data-location\"></testcase>\n  \
+      </testsuite>\n  \
+      <testsuite name=\"web-rg\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"1\">\n    \
+      <properties>\n      <property name=\"score\" value=\"0.0\"/>\n    </properties>\n    \
+      <testcase classname=\"app_service.app\" name=\"location\">\n      \
+      <failure type=\"noncompliant\" message=\"expected location = &quot;uksouth&quot;, found &quot;westeurope&quot;\">\
+      rule: location\nseverity: medium\nexpected: location = &quot;uksouth&quot;\nactual: &quot;westeurope&quot;</failure>\n    \
+      </testcase>\n    \
+      <testcase classname=\"app_service.app\" name=\"data-location\">\n      \
+      <skipped message=\"azure.data-rg doesn&apos;t apply to this resource\"/>\n    </testcase>\n  \
+      </testsuite>\n\
+      </testsuites>\n");
+  }
+
+  #[test]
+  fn test_not_applicable_cases() {
+    let resources: Vec<Resource> = ["app", "api"].iter()
+      .map(|name| Resource::try_from(json!({
+        "id": format!("/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/{}", name),
+        "location": "uksouth",
+      })).unwrap())
+      .collect();
+    let rules = parse_source(
+      "azure.data-rg {\n  @id(\"data-location\") location = \"uksouth\"\n  @id(\"data-name\") name ~= /^data/\n}\n\
+        azure.*.app_service.app {\n  @id(\"app-location\") location = \"uksouth\"\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    let xml = render(&Engine::new().at(now).evaluate(&resources, &rules));
+    let skipped: Vec<&str> = xml.split("<testcase ")
+      .filter(|case| case.contains("<skipped "))
+      .map(|case| case.split('>').next().unwrap())
+      .collect();
+
+    // One skipped case per rule and resource it doesn't apply to
+    assert_eq!(skipped, vec![
+      "classname=\"app_service.app\" name=\"data-location\"",
+      "classname=\"app_service.app\" name=\"data-name\"",
+      "classname=\"app_service.api\" name=\"data-location\"",
+      "classname=\"app_service.api\" name=\"data-name\"",
+      "classname=\"app_service.api\" name=\"app-location\"",
+    ]);
+    assert!(xml.contains("<testsuite name=\"web-rg\" tests=\"6\" failures=\"0\" errors=\"0\" skipped=\"5\">"));
+    assert!(xml.contains("<skipped message=\"azure.*.app_service.app doesn&apos;t apply to this resource\"/>"));
+  }
+}
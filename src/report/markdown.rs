@@ -0,0 +1,201 @@
+use crate::engine::{ Outcome, ResourceCompliance, ResourceGroupCompliance };
+use crate::rules::Severity;
+use crate::scoring::Breakdown;
+use std::collections::{ BTreeMap, HashMap };
+
+// A compact summary for pull request comments: the score, new violations by severity,
+// the most violated rules and a collapsible section per resource with violations or
+// errors. Sections are cut short with a notice rather than exceed the comment size limit.
+
+// GitHub rejects comments over 65536 characters; leave room for anything the pipeline adds
+pub const MAX_LENGTH: usize = 60_000;
+
+const TOP_RULES: usize = 10;
+
+// Room kept at the end of a truncated section for its notice
+const NOTICE_LENGTH: usize = 100;
+
+const GROUPS_FOOTER: &str = "\n</details>\n";
+const RESOURCES_HEADER: &str = "\n#### Resources\n\n";
+
+fn cell(value: &str) -> String {
+  value.replace('|', "\\|")
+}
+
+fn resource_details(resource: &ResourceCompliance) -> Option<String> {
+  let lines: Vec<String> = resource.evaluations.iter()
+    .filter_map(|evaluation| {
+      let rule = &evaluation.rule;
+      let line = format!("- `{}`: found `{}` ({}, {})", rule, evaluation.found(), rule.id(), rule.metadata.severity);
+
+      match &evaluation.outcome {
+        Outcome::NonCompliant { .. } => Some(line),
+        Outcome::Baselined { .. } => Some(format!("{} _baselined_", line)),
+        Outcome::Error { reason } => Some(format!("{} _error: {}_", line, reason)),
+        _ => None,
+      }
+    })
+    .collect();
+
+  if lines.is_empty() {
+    return None;
+  }
+
+  Some(format!(
+    "<details><summary>{} ({}): {} findings</summary>\n\n{}\n\n</details>\n",
+    resource.resource_name, resource.resource_type, lines.len(), lines.join("\n")
+  ))
+}
+
+// Appends as many of `sections` as fit in `max_length`, followed by a notice of how many
+// `things` were left out
+fn push_within(markdown: &mut String, sections: &[String], max_length: usize, things: &str) {
+  let budget = max_length.saturating_sub(markdown.len() + NOTICE_LENGTH);
+  let mut used = 0;
+
+  for (shown, section) in sections.iter().enumerate() {
+    if used + section.len() > budget {
+      markdown.push_str(&format!(
+        "\n_{} more {} not shown to fit the comment size limit; see the full report._\n",
+        sections.len() - shown, things
+      ));
+      return;
+    }
+
+    used += section.len();
+    markdown.push_str(section);
+  }
+}
+
+pub fn render(results: &[ResourceCompliance]) -> String {
+  render_within(results, MAX_LENGTH)
+}
+
+fn render_within(results: &[ResourceCompliance], max_length: usize) -> String {
+  let group = ResourceGroupCompliance::from_results(results);
+  let breakdown = Breakdown::from_results(results);
+
+  let mut severities: BTreeMap<Severity, usize> = BTreeMap::new();
+  let mut rule_severities: HashMap<String, Severity> = HashMap::new();
+  for evaluation in results.iter().flat_map(|r| &r.evaluations) {
+    rule_severities.entry(evaluation.rule.id()).or_insert(evaluation.rule.metadata.severity);
+    if let Outcome::NonCompliant { .. } = evaluation.outcome {
+      *severities.entry(evaluation.rule.metadata.severity).or_default() += 1;
+    }
+  }
+
+  let mut markdown = format!(
+    "### cloud-lint: {:.0}% compliant\n\n**{}** new violations, {} baselined, {} waived, {} errors across {} resources.\n\n",
+    breakdown.overall.percentage(),
+    group.noncompliant_rule_evaluations,
+    group.baselined_rule_evaluations,
+    group.waived_rule_evaluations,
+    group.error_rule_evaluations,
+    group.resource_count,
+  );
+
+  markdown.push_str("| Severity | New violations |\n| --- | --- |\n");
+  for severity in [Severity::Critical, Severity::High, Severity::Medium, Severity::Low] {
+    markdown.push_str(&format!("| {} | {} |\n", severity, severities.get(&severity).unwrap_or(&0)));
+  }
+
+  // Most violated first, then by severity
+  let mut failing: Vec<(&String, usize, Option<Severity>)> = breakdown.rules.iter()
+    .map(|(id, score)| (id, score.noncompliant + score.baselined, rule_severities.get(id).copied()))
+    .filter(|(_, violations, _)| *violations > 0)
+    .collect();
+  failing.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)));
+
+  if !failing.is_empty() {
+    markdown.push_str("\n#### Top failing rules\n\n| Rule | Severity | Violations | Score |\n| --- | --- | --- | --- |\n");
+
+    for (id, violations, severity) in failing.iter().take(TOP_RULES) {
+      let severity = severity.map(|s| s.to_string()).unwrap_or_default();
+      markdown.push_str(&format!("| `{}` | {} | {} | {:.0}% |\n", cell(id), severity, violations, breakdown.rules[*id].percentage()));
+    }
+
+    if failing.len() > TOP_RULES {
+      markdown.push_str(&format!("\n_…and {} more failing rules._\n", failing.len() - TOP_RULES));
+    }
+  }
+
+  let groups: Vec<String> = breakdown.groups.iter()
+    .map(|(key, score)| format!("| {} | {:.0}% |\n", cell(key), score.percentage()))
+    .collect();
+  let details: Vec<String> = results.iter().filter_map(resource_details).collect();
+
+  // The group table leaves room for the resources section's header and notice
+  let reserved = GROUPS_FOOTER.len() + if details.is_empty() { 0 } else { RESOURCES_HEADER.len() + NOTICE_LENGTH };
+
+  markdown.push_str("\n<details><summary>Scores by resource group</summary>\n\n| Group | Score |\n| --- | --- |\n");
+  push_within(&mut markdown, &groups, max_length.saturating_sub(reserved), "resource groups");
+  markdown.push_str(GROUPS_FOOTER);
+
+  if !details.is_empty() {
+    markdown.push_str(RESOURCES_HEADER);
+    push_within(&mut markdown, &details, max_length, "resources");
+  }
+
+  markdown
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use chrono::{ TimeZone, Utc };
+  use serde_json::json;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_render() {
+    let resources: Vec<Resource> = ["app", "api", "web"].iter()
+      .map(|name| Resource::try_from(json!({
+        "id": format!("/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/{}", name),
+        "location": "westeurope",
+      })).unwrap())
+      .collect();
+    let rules = parse_source(
+      "azure.* {\n  @id(\"location\") @severity(\"high\") location = \"uksouth\"\n  @id(\"name\") name ~= /^a/\n}\n"
+    ).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).evaluate(&resources, &rules);
+
+    let markdown = render(&results);
+
+    assert!(markdown.starts_with("### cloud-lint: 22% compliant\n\n**4** new violations, 0 baselined, 0 waived, 0 errors across 3 resources.\n"));
+    assert!(markdown.contains("| critical | 0 |\n| high | 3 |\n| medium | 1 |\n| low | 0 |\n"));
+    assert!(markdown.contains("| `location` | high | 3 | 0% |\n| `name` | medium | 1 | 67% |\n"));
+    assert!(markdown.contains(
+      "<details><summary>app (app_service): 1 findings</summary>\n\n- `location = \"uksouth\"`: found `\"westeurope\"` (location, high)\n\n</details>\n"
+    ));
+
+    // Only as many resources as fit are listed
+    let full = markdown.len();
+    let truncated = render_within(&results, full - 50);
+    assert!(truncated.len() <= full - 50);
+    assert!(truncated.contains("_1 more resources not shown to fit the comment size limit; see the full report._"));
+  }
+
+  #[test]
+  fn test_render_limits_group_scores() {
+    let resources: Vec<Resource> = (0..40)
+      .map(|i| Resource::try_from(json!({
+        "id": format!("/subscriptions/sub-a/resourceGroups/group-{:02}/providers/Microsoft.Web/sites/app", i),
+        "location": "westeurope",
+      })).unwrap())
+      .collect();
+    let rules = parse_source("azure.* {\n  @id(\"location\") location = \"uksouth\"\n}\n").unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).evaluate(&resources, &rules);
+
+    let markdown = render_within(&results, 1000);
+
+    assert!(markdown.len() <= 1000);
+    assert!(markdown.contains("more resource groups not shown to fit the comment size limit"));
+    assert!(markdown.contains("_40 more resources not shown to fit the comment size limit; see the full report._"));
+    assert!(markdown.ends_with("</details>\n\n#### Resources\n\n\n_40 more resources not shown to fit the comment size limit; see the full report._\n"));
+  }
+}
@@ -0,0 +1,45 @@
+// Machine-readable renderings of lint results, selected with `--format`. The `text`
+// report is written by the binary itself.
+
+pub mod html;
+pub mod json;
+pub mod junit;
+pub mod markdown;
+pub mod sarif;
+
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+  Text,
+  Junit,
+  Sarif,
+  Json,
+  Html,
+  Markdown,
+}
+
+impl TryFrom<&str> for Format {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    match value {
+      "text" => Ok(Format::Text),
+      "junit" => Ok(Format::Junit),
+      "sarif" => Ok(Format::Sarif),
+      "json" => Ok(Format::Json),
+      "html" => Ok(Format::Html),
+      "markdown" => Ok(Format::Markdown),
+      _ => Err("Unknown report format"),
+    }
+  }
+}
+
+pub(crate) fn xml_escape(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
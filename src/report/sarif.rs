@@ -0,0 +1,177 @@
+use crate::engine::{ Outcome, ResourceCompliance };
+use crate::rules::{ Rule, Severity };
+use crate::scoring::{ Breakdown, Score };
+use serde_json::{ json, Map, Value };
+use std::collections::BTreeMap;
+
+// A SARIF 2.1.0 log with a single run. Each rule is a reporting descriptor, and each new,
+// baselined or waived violation a result located at its ARM resource id. Waived results
+// carry a suppression and baselined ones `"baselineState": "unchanged"`, so dashboards
+// can hide them.
+
+fn level(severity: Severity) -> &'static str {
+  match severity {
+    Severity::Critical | Severity::High => "error",
+    Severity::Medium => "warning",
+    Severity::Low => "note",
+  }
+}
+
+// The 0-10 score code scanning dashboards rank security findings by
+fn security_severity(severity: Severity) -> &'static str {
+  match severity {
+    Severity::Critical => "9.5",
+    Severity::High => "8.0",
+    Severity::Medium => "5.0",
+    Severity::Low => "2.0",
+  }
+}
+
+fn descriptor(rule: &Rule) -> Value {
+  let severity = rule.metadata.severity;
+  let mut descriptor = json!({
+    "id": rule.id(),
+    "shortDescription": { "text": format!("{} {}", rule.selector, rule) },
+    "defaultConfiguration": { "level": level(severity) },
+    "properties": { "severity": severity.to_string(), "security-severity": security_severity(severity) },
+  });
+
+  if let Some(remediation) = &rule.metadata.remediation {
+    descriptor["help"] = json!({ "text": remediation });
+  }
+
+  descriptor
+}
+
+fn scores(scores: &BTreeMap<String, Score>) -> Value {
+  Value::Object(scores.iter().map(|(key, score)| (key.clone(), json!(score.percentage()))).collect::<Map<_, _>>())
+}
+
+// `source` is the URI of the local file the resources were read from, if any
+pub fn render(results: &[ResourceCompliance], rules: &[Rule], source: Option<&str>) -> String {
+  let descriptors: Vec<Value> = rules.iter().map(descriptor).collect();
+  let indexes: BTreeMap<String, usize> = rules.iter().enumerate().map(|(i, rule)| (rule.id(), i)).collect();
+
+  let mut sarif_results: Vec<Value> = Vec::new();
+
+  for resource in results {
+    for evaluation in &resource.evaluations {
+      let rule = &evaluation.rule;
+      let mut result = json!({
+        "ruleId": rule.id(),
+        "level": level(rule.metadata.severity),
+        "message": { "text": format!("{}: expected {}, found {}", resource.resource_name, rule, evaluation.found()) },
+        "locations": [{
+          "logicalLocations": [{ "name": resource.resource_name, "fullyQualifiedName": resource.resource_id, "kind": "resource" }],
+        }],
+      });
+
+      match &evaluation.outcome {
+        Outcome::NonCompliant { .. } => {}
+        Outcome::Baselined { .. } => result["baselineState"] = json!("unchanged"),
+        Outcome::Waived { waiver, .. } => {
+          result["suppressions"] = json!([{
+            "kind": "external",
+            "justification": format!("{} (waived until {})", waiver.justification, waiver.expires),
+          }]);
+        }
+        Outcome::Compliant | Outcome::NotApplicable | Outcome::Error { .. } => continue,
+      }
+
+      if let Some(&index) = indexes.get(&rule.id()) {
+        result["ruleIndex"] = json!(index);
+      }
+
+      if let Some(uri) = source {
+        result["locations"][0]["physicalLocation"] = json!({ "artifactLocation": { "uri": uri } });
+      }
+
+      sarif_results.push(result);
+    }
+  }
+
+  let breakdown = Breakdown::from_results(results);
+  let log = json!({
+    "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+    "version": "2.1.0",
+    "runs": [{
+      "tool": {
+        "driver": {
+          "name": "cloud-lint",
+          "version": env!("CARGO_PKG_VERSION"),
+          "rules": descriptors,
+        },
+      },
+      "results": sarif_results,
+      "properties": {
+        "score": breakdown.overall.percentage(),
+        "subscriptions": scores(&breakdown.subscriptions),
+        "groups": scores(&breakdown.groups),
+        "kinds": scores(&breakdown.kinds),
+        "rules": scores(&breakdown.rules),
+      },
+    }],
+  });
+
+  serde_json::to_string_pretty(&log).unwrap_or_default() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser::parse_source;
+  use crate::waivers::Waiver;
+  use chrono::{ TimeZone, Utc };
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_render() {
+    let resources: Vec<Resource> = ["app", "legacy-app", "new-app"].iter()
+      .map(|name| Resource::try_from(json!({
+        "id": format!("/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/{}", name),
+        "location": if *name == "app" { "uksouth" } else { "westeurope" },
+      })).unwrap())
+      .collect();
+    let rules = parse_source(
+      "azure.* {\n  @id(\"location\") @severity(\"high\") @remediation(\"Redeploy to uksouth\")\n  location = \"uksouth\"\n}\n"
+    ).unwrap();
+    let waiver = Waiver::try_from(&json!({
+      "rule": "location", "resource": "azure.web-rg.app_service.legacy-app", "justification": "Migrating in Q1", "expires": "2027-01-01",
+    })).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let results = Engine::new().at(now).with_waivers(vec![waiver]).evaluate(&resources, &rules);
+
+    let sarif: Value = serde_json::from_str(&render(&results, &rules, Some("resources.json"))).unwrap();
+    let run = &sarif["runs"][0];
+
+    assert_eq!(run["tool"]["driver"]["rules"], json!([{
+      "id": "location",
+      "shortDescription": { "text": "azure.* location = \"uksouth\"" },
+      "defaultConfiguration": { "level": "error" },
+      "properties": { "severity": "high", "security-severity": "8.0" },
+      "help": { "text": "Redeploy to uksouth" },
+    }]));
+
+    // The compliant app has no result
+    let results = run["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["suppressions"][0]["justification"], "Migrating in Q1 (waived until 2027-01-01)");
+    assert_eq!(results[1], json!({
+      "ruleId": "location",
+      "ruleIndex": 0,
+      "level": "error",
+      "message": { "text": "new-app: expected location = \"uksouth\", found \"westeurope\"" },
+      "locations": [{
+        "logicalLocations": [{
+          "name": "new-app",
+          "fullyQualifiedName": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/new-app",
+          "kind": "resource",
+        }],
+        "physicalLocation": { "artifactLocation": { "uri": "resources.json" } },
+      }],
+    }));
+    assert_eq!(run["properties"]["groups"]["web-rg"], json!(50.0));
+  }
+}
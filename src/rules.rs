@@ -1,5 +1,10 @@
+use crate::azurerm::select_path;
+use crate::network::{ self, Network };
+use chrono::{ DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc };
 use regex::Regex;
-use std::convert::TryFrom;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::convert::{ TryFrom, TryInto };
 use std::fmt;
 
 // Selector (cloud.group.type.name)
@@ -13,6 +18,16 @@ pub struct Selector {
   full_selector: String,
 }
 
+impl Selector {
+  // Whether the selector matches a resource with these parts, exactly or by `*` wildcard
+  pub fn applies_to(&self, cloud: &str, group: &str, kind: &str, name: &str) -> bool {
+    (self.cloud == cloud || self.cloud == "*") &&
+    (self.group == group || self.group == "*") &&
+    (self.kind == kind || self.kind == "*") &&
+    (self.name == name || self.name == "*")
+  }
+}
+
 impl TryFrom<&'_ str> for Selector {
   type Error = &'static str;
 
@@ -67,7 +82,7 @@ impl fmt::Display for Selector {
 }
 
 // Property
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Property {
   Name,
   Kind,
@@ -108,46 +123,769 @@ impl TryFrom<&String> for Property {
   }
 }
 
+impl fmt::Display for Property {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Name => write!(f, "name"),
+      Self::Kind => write!(f, "type"),
+      Self::Group => write!(f, "group"),
+      Self::Custom(path) => write!(f, "{}", path),
+    }
+  }
+}
+
+// Quantifier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantifier {
+  Any,
+  All,
+  None,
+}
+
+impl Quantifier {
+  pub fn is_compliant(&self, values: &[Value], condition: &Condition) -> bool {
+    let mut results = values.iter().map(|value| condition.is_compliant(value));
+
+    match self {
+      Self::Any => results.any(|compliant| compliant),
+      Self::All => results.all(|compliant| compliant),
+      Self::None => !results.any(|compliant| compliant),
+    }
+  }
+}
+
+impl TryFrom<&str> for Quantifier {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    match value {
+      "any" => Ok(Self::Any),
+      "all" => Ok(Self::All),
+      "none" => Ok(Self::None),
+      _ => Err("Unknown quantifier"),
+    }
+  }
+}
+
+impl fmt::Display for Quantifier {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Any => write!(f, "any"),
+      Self::All => write!(f, "all"),
+      Self::None => write!(f, "none"),
+    }
+  }
+}
+
+// Function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+  Length, // characters in a string, elements in an array or entries in an object
+  Count,  // like length, but a missing value counts 0, a scalar 1 and nulls are skipped
+  Lower,
+  Upper,
+  Trim,
+  NormalizeLocation, // "UK South", "UKSouth" and "uksouth" all become "uksouth"
+  Version,           // marks a value for version ordering; numbers (e.g. 1.2) become strings
+  IsPublicIp,        // whether an address, CIDR range or NSG service tag reaches outside private/link-local space
+}
+
+impl Function {
+  pub fn apply(&self, value: &Value) -> Value {
+    match (self, value) {
+      // String transforms map over the elements of wildcard results
+      (Self::Lower, _) | (Self::Upper, _) | (Self::Trim, _) | (Self::NormalizeLocation, _) => {
+        self.transform(value)
+      }
+
+      (Self::Length, Value::String(s)) => s.chars().count().into(),
+      (Self::Length, Value::Array(elements)) => elements.len().into(),
+      (Self::Length, Value::Object(entries)) => entries.len().into(),
+      (Self::Length, _) => Value::Null,
+
+      (Self::Count, Value::Array(elements)) => elements.iter().filter(|e| !e.is_null()).count().into(),
+      (Self::Count, Value::Object(entries)) => entries.values().filter(|e| !e.is_null()).count().into(),
+      (Self::Count, Value::Null) => 0.into(),
+      (Self::Count, _) => 1.into(),
+
+      (Self::Version, Value::String(_)) => value.clone(),
+      (Self::Version, Value::Number(n)) => n.to_string().into(),
+      (Self::Version, Value::Array(elements)) => Value::Array(elements.iter().map(|e| self.apply(e)).collect()),
+      (Self::Version, _) => Value::Null,
+
+      (Self::IsPublicIp, Value::String(s)) => network::is_public(s).map_or(Value::Null, Value::from),
+      (Self::IsPublicIp, Value::Array(elements)) => Value::Array(elements.iter().map(|e| self.apply(e)).collect()),
+      (Self::IsPublicIp, _) => Value::Null,
+    }
+  }
+
+  fn transform(&self, value: &Value) -> Value {
+    match value {
+      Value::String(s) => match self {
+        Self::Lower => s.to_lowercase(),
+        Self::Upper => s.to_uppercase(),
+        Self::Trim => s.trim().to_owned(),
+        _ => s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase(),
+      }.into(),
+      Value::Array(elements) => Value::Array(elements.iter().map(|e| self.transform(e)).collect()),
+      _ => Value::Null,
+    }
+  }
+}
+
+impl TryFrom<&str> for Function {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    match value {
+      "len" => Ok(Self::Length),
+      "count" => Ok(Self::Count),
+      "lower" => Ok(Self::Lower),
+      "upper" => Ok(Self::Upper),
+      "trim" => Ok(Self::Trim),
+      "normalize_location" => Ok(Self::NormalizeLocation),
+      "version" => Ok(Self::Version),
+      "is_public_ip" => Ok(Self::IsPublicIp),
+      _ => Err("Unknown function"),
+    }
+  }
+}
+
+impl fmt::Display for Function {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Length => write!(f, "len"),
+      Self::Count => write!(f, "count"),
+      Self::Lower => write!(f, "lower"),
+      Self::Upper => write!(f, "upper"),
+      Self::Trim => write!(f, "trim"),
+      Self::NormalizeLocation => write!(f, "normalize_location"),
+      Self::Version => write!(f, "version"),
+      Self::IsPublicIp => write!(f, "is_public_ip"),
+    }
+  }
+}
+
+// Expression (the left-hand side of a rule)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+  Property(Property),
+  Quantified(Quantifier, Box<Expression>),
+  Call(Function, Box<Expression>),
+  Reference(Box<Expression>, Property), // a property of the resource(s) whose ids the expression yields
+  ReferencedBy(String, Property),       // ids of resources of a kind whose property refers to this one
+}
+
+impl Expression {
+  // Evaluates the expression relative to a JSON value, such as an array element, rather than
+  // a resource. References need the other resources, so they yield null.
+  pub fn evaluate_on(&self, value: &Value) -> Value {
+    match self {
+      Self::Property(Property::Custom(path)) => select_path(value, path),
+      Self::Property(Property::Name) => value["name"].clone(),
+      Self::Property(Property::Kind) => value["type"].clone(),
+      Self::Property(Property::Group) => Value::Null,
+      Self::Quantified(_, inner) => inner.evaluate_on(value),
+      Self::Call(function, inner) => function.apply(&inner.evaluate_on(value)),
+      Self::Reference(..) | Self::ReferencedBy(..) => Value::Null,
+    }
+  }
+
+  pub fn is_quantified(&self) -> bool {
+    match self {
+      Self::Quantified(..) => true,
+      Self::Call(_, inner) | Self::Reference(inner, _) => inner.is_quantified(),
+      Self::Property(_) | Self::ReferencedBy(..) => false,
+    }
+  }
+}
+
+impl From<Property> for Expression {
+  fn from(property: Property) -> Self {
+    Self::Property(property)
+  }
+}
+
+impl fmt::Display for Expression {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Property(property) => write!(f, "{}", property),
+      Self::Quantified(quantifier, inner) => write!(f, "{}({})", quantifier, inner),
+      Self::Call(function, inner) => write!(f, "{}({})", function, inner),
+      Self::Reference(inner, property) => write!(f, "ref({}).{}", inner, property),
+      Self::ReferencedBy(kind, property) => write!(f, "referenced_by({}, {})", kind, property),
+    }
+  }
+}
+
+// Version
+//
+// The numeric components of a version string, ignoring any prefix or suffix, so that
+// "1.2", "TLS1_2", "NODE|14-lts" and "v1.27.3" can be ordered. Missing components are 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Version(pub Vec<u64>);
+
+impl Version {
+  pub fn compare(&self, other: &Version) -> std::cmp::Ordering {
+    let length = self.0.len().max(other.0.len());
+    let component = |version: &Version, i: usize| version.0.get(i).copied().unwrap_or(0);
+
+    (0..length)
+      .map(|i| component(self, i).cmp(&component(other, i)))
+      .find(|ordering| *ordering != std::cmp::Ordering::Equal)
+      .unwrap_or(std::cmp::Ordering::Equal)
+  }
+}
+
+impl TryFrom<&str> for Version {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    let start = value.find(|c: char| c.is_ascii_digit()).ok_or("No version number")?;
+    let numeric = &value[start..];
+    let end = numeric.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_')).unwrap_or(numeric.len());
+
+    numeric[..end]
+      .split(['.', '_'])
+      .filter(|component| !component.is_empty())
+      .map(|component| component.parse::<u64>().map_err(|_| "Invalid version component"))
+      .collect::<Result<Vec<u64>, _>>()
+      .map(Self)
+  }
+}
+
+impl fmt::Display for Version {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let components: Vec<String> = self.0.iter().map(u64::to_string).collect();
+
+    write!(f, "{}", components.join("."))
+  }
+}
+
+// Template (a right-hand side string with `${property}` placeholders)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+  Text(String),
+  Property(Property),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template(pub Vec<Segment>);
+
+impl Template {
+  pub fn is_template(value: &str) -> bool {
+    value.contains("${")
+  }
+
+  // Substitutes each placeholder with the (scalar) property value returned by `lookup`,
+  // passing it through `escape` first. Missing or structured values yield `None`.
+  pub fn render(&self, lookup: &dyn Fn(&Property) -> Value, escape: fn(&str) -> String) -> Option<String> {
+    let mut rendered = String::new();
+
+    for segment in &self.0 {
+      match segment {
+        Segment::Text(text) => rendered.push_str(text),
+        Segment::Property(property) => match lookup(property) {
+          Value::String(s) => rendered.push_str(&escape(&s)),
+          Value::Number(n) => rendered.push_str(&escape(&n.to_string())),
+          Value::Bool(b) => rendered.push_str(&escape(&b.to_string())),
+          _ => return None,
+        },
+      }
+    }
+
+    Some(rendered)
+  }
+}
+
+impl TryFrom<&str> for Template {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    let mut segments = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+      let end = rest[start..].find('}').ok_or("Unterminated ${ in template")? + start;
+
+      if start > 0 {
+        segments.push(Segment::Text(rest[..start].to_owned()));
+      }
+      segments.push(Segment::Property(rest[start + 2..end].trim().try_into()?));
+      rest = &rest[end + 1..];
+    }
+
+    if !rest.is_empty() {
+      segments.push(Segment::Text(rest.to_owned()));
+    }
+
+    Ok(Self(segments))
+  }
+}
+
+impl From<Property> for Template {
+  fn from(property: Property) -> Self {
+    Self(vec![Segment::Property(property)])
+  }
+}
+
+impl fmt::Display for Template {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for segment in &self.0 {
+      match segment {
+        Segment::Text(text) => write!(f, "{}", text)?,
+        Segment::Property(property) => write!(f, "${{{}}}", property)?,
+      }
+    }
+
+    Ok(())
+  }
+}
+
 // Condition
 #[derive(Debug, Clone)]
 pub enum Condition {
   Equal(String),
+  EqualBool(bool),
+  EqualIgnoreCase(String),
   Match(Regex),
+  LessThan(f64),
+  LessOrEqual(f64),
+  GreaterThan(f64),
+  GreaterOrEqual(f64),
+  VersionLessThan(Version),
+  VersionLessOrEqual(Version),
+  VersionGreaterThan(Version),
+  VersionGreaterOrEqual(Version),
+  Within(Network),   // an address or range lies inside the network
+  Overlaps(Network), // an address or range shares at least one address with the network
+  Before(DateTime<Utc>),
+  Not(Box<Condition>),
+  Element(Vec<(Expression, Condition)>), // every clause holds, evaluated relative to an array element
+
+  // Resolved against the evaluation time into `Before`
+  OlderThan(Duration),
+  WithinDuration(Duration), // due by now + the duration, so dates already past count as within
+
+  // Resolved per-resource into the equivalent condition above
+  EqualTemplate(Template),
+  EqualIgnoreCaseTemplate(Template),
+  MatchTemplate(Template),
 }
 
 impl Condition {
-  pub fn is_compliant(&self, value: &str) -> bool {
+  // Produces the concrete condition for one resource at time `now`, substituting property
+  // references from `lookup`. Returns `None` when a referenced property has no usable value.
+  pub fn resolve(&self, lookup: &dyn Fn(&Property) -> Value, now: DateTime<Utc>) -> Option<Condition> {
     match self {
-      Self::Equal(expected) => expected == value,
-      Self::Match(regex) => regex.is_match(value),
+      Self::OlderThan(age) => Some(Self::Before(now - *age)),
+      // Only looks forward: a certificate that expired last month is as much a problem as one
+      // expiring next week, so `not within 30d` flags both
+      Self::WithinDuration(period) => Some(Self::Before(now + *period)),
+      Self::Not(inner) => inner.resolve(lookup, now).map(|inner| Self::Not(Box::new(inner))),
+      Self::Element(clauses) => clauses.iter()
+        .map(|(expression, condition)| Some((expression.clone(), condition.resolve(lookup, now)?)))
+        .collect::<Option<Vec<_>>>()
+        .map(Self::Element),
+      Self::EqualTemplate(template) => template.render(lookup, str::to_owned).map(Self::Equal),
+      Self::EqualIgnoreCaseTemplate(template) => {
+        template.render(lookup, str::to_owned).map(Self::EqualIgnoreCase)
+      }
+      Self::MatchTemplate(template) => template.render(lookup, regex::escape)
+        .and_then(|pattern| Regex::new(&pattern).ok())
+        .map(Self::Match),
+      condition => Some(condition.clone()),
+    }
+  }
+
+  // The JSON type this condition compares against; any other type is a rule error
+  // (e.g. a path that points at an object) rather than a violation
+  pub fn value_type(&self) -> &'static str {
+    match self {
+      Self::EqualBool(_) => "boolean",
+      Self::LessThan(_) | Self::LessOrEqual(_) | Self::GreaterThan(_) | Self::GreaterOrEqual(_) => "number",
+      Self::Not(inner) => inner.value_type(),
+      Self::Element(_) => "object",
+      _ => "string",
+    }
+  }
+
+  // What a string must parse as for the comparison to be meaningful, e.g. "date"
+  pub fn value_kind(&self) -> &'static str {
+    match self {
+      Self::VersionLessThan(_) | Self::VersionLessOrEqual(_) | Self::VersionGreaterThan(_) | Self::VersionGreaterOrEqual(_) => "version",
+      Self::Within(_) | Self::Overlaps(_) => "IP address or range",
+      Self::Before(_) | Self::OlderThan(_) | Self::WithinDuration(_) => "date",
+      Self::Not(inner) => inner.value_kind(),
+      _ => self.value_type(),
+    }
+  }
+
+  fn parses(&self, value: &str) -> bool {
+    match self.value_kind() {
+      "version" => Version::try_from(value).is_ok(),
+      "IP address or range" => Network::try_from(value).is_ok(),
+      "date" => parse_timestamp(value).is_some(),
+      _ => true,
+    }
+  }
+
+  // A value that doesn't parse fails a comparison, but negating that failure would make the
+  // rule pass on garbage, so under `not` it's an error instead
+  pub fn accepts(&self, value: &Value) -> bool {
+    self.value_type() == type_name(value) && match (self, value) {
+      (Self::Not(inner), Value::String(v)) => inner.parses(v),
+      _ => true,
+    }
+  }
+
+  // The one value that satisfies this condition, if there is exactly one (e.g. `= "uksouth"`)
+  pub fn desired_value(&self) -> Option<Value> {
+    match self {
+      Self::Equal(x) | Self::EqualIgnoreCase(x) => Some(Value::String(x.to_owned())),
+      Self::EqualBool(x) => Some(Value::Bool(*x)),
+      _ => None,
+    }
+  }
+
+  pub fn is_compliant(&self, value: &Value) -> bool {
+    match (self, value) {
+      (Self::Equal(expected), Value::String(value)) => expected == value,
+      (Self::EqualBool(expected), Value::Bool(value)) => expected == value,
+      (Self::EqualIgnoreCase(expected), Value::String(value)) => expected.to_lowercase() == value.to_lowercase(),
+      (Self::Match(regex), Value::String(value)) => regex.is_match(value),
+      (Self::LessThan(bound), Value::Number(n)) => matches!(n.as_f64(), Some(n) if n < *bound),
+      (Self::LessOrEqual(bound), Value::Number(n)) => matches!(n.as_f64(), Some(n) if n <= *bound),
+      (Self::GreaterThan(bound), Value::Number(n)) => matches!(n.as_f64(), Some(n) if n > *bound),
+      (Self::GreaterOrEqual(bound), Value::Number(n)) => matches!(n.as_f64(), Some(n) if n >= *bound),
+      (Self::VersionLessThan(bound), Value::String(v)) => matches!(compare_version(v, bound), Some(ordering) if ordering.is_lt()),
+      (Self::VersionLessOrEqual(bound), Value::String(v)) => matches!(compare_version(v, bound), Some(ordering) if ordering.is_le()),
+      (Self::VersionGreaterThan(bound), Value::String(v)) => matches!(compare_version(v, bound), Some(ordering) if ordering.is_gt()),
+      (Self::VersionGreaterOrEqual(bound), Value::String(v)) => matches!(compare_version(v, bound), Some(ordering) if ordering.is_ge()),
+      (Self::Within(network), Value::String(v)) => matches!(Network::try_from(v.as_str()), Ok(n) if n.is_within(network)),
+      (Self::Overlaps(network), Value::String(v)) => matches!(Network::try_from(v.as_str()), Ok(n) if n.overlaps(network)),
+      (Self::Before(bound), Value::String(v)) => matches!(parse_timestamp(v), Some(t) if t < *bound),
+      (Self::Not(inner), value) => !inner.is_compliant(value),
+      (Self::Element(clauses), Value::Object(_)) => clauses.iter().all(|(expression, condition)| holds(expression, condition, value)),
+      _ => false,
     }
   }
 }
 
+// Whether a clause of an element condition holds for `element`. A clause whose property is
+// missing or of the wrong type doesn't hold.
+fn holds(expression: &Expression, condition: &Condition, element: &Value) -> bool {
+  match (expression, expression.evaluate_on(element)) {
+    (Expression::Quantified(quantifier, _), Value::Array(values)) => quantifier.is_compliant(&values, condition),
+    (Expression::Quantified(quantifier, _), value) => quantifier.is_compliant(&[value], condition),
+    (_, value) => condition.accepts(&value) && condition.is_compliant(&value),
+  }
+}
+
+pub fn type_name(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "boolean",
+    Value::Number(_) => "number",
+    Value::String(_) => "string",
+    Value::Array(_) => "array",
+    Value::Object(_) => "object",
+  }
+}
+
+// ISO-8601 timestamps as returned by ARM (with or without an offset), or plain dates
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+  DateTime::parse_from_rfc3339(value).map(|t| t.with_timezone(&Utc)).ok()
+    .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").ok().map(|t| Utc.from_utc_datetime(&t)))
+    .or_else(|| {
+      let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+
+      Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?))
+    })
+}
+
+fn describe_duration(duration: &Duration) -> String {
+  match (duration.num_days(), duration.num_hours()) {
+    (days, hours) if hours == days * 24 => format!("{}d", days),
+    (_, hours) => format!("{}h", hours),
+  }
+}
+
+fn compare_version(value: &str, bound: &Version) -> Option<Ordering> {
+  Version::try_from(value).ok().map(|version| version.compare(bound))
+}
+
 impl PartialEq for Condition {
   fn eq(&self, other: &Self) -> bool {
     match (self, other) {
       (Self::Equal(a), Self::Equal(b)) => a == b,
+      (Self::EqualBool(a), Self::EqualBool(b)) => a == b,
+      (Self::EqualIgnoreCase(a), Self::EqualIgnoreCase(b)) => a == b,
       (Self::Match(a), Self::Match(b)) => a.as_str() == b.as_str(),
+      (Self::LessThan(a), Self::LessThan(b)) => a == b,
+      (Self::LessOrEqual(a), Self::LessOrEqual(b)) => a == b,
+      (Self::GreaterThan(a), Self::GreaterThan(b)) => a == b,
+      (Self::GreaterOrEqual(a), Self::GreaterOrEqual(b)) => a == b,
+      (Self::VersionLessThan(a), Self::VersionLessThan(b)) => a == b,
+      (Self::VersionLessOrEqual(a), Self::VersionLessOrEqual(b)) => a == b,
+      (Self::VersionGreaterThan(a), Self::VersionGreaterThan(b)) => a == b,
+      (Self::VersionGreaterOrEqual(a), Self::VersionGreaterOrEqual(b)) => a == b,
+      (Self::Within(a), Self::Within(b)) => a == b,
+      (Self::Overlaps(a), Self::Overlaps(b)) => a == b,
+      (Self::Before(a), Self::Before(b)) => a == b,
+      (Self::Not(a), Self::Not(b)) => a == b,
+      (Self::Element(a), Self::Element(b)) => a == b,
+      (Self::OlderThan(a), Self::OlderThan(b)) => a == b,
+      (Self::WithinDuration(a), Self::WithinDuration(b)) => a == b,
+      (Self::EqualTemplate(a), Self::EqualTemplate(b)) => a == b,
+      (Self::EqualIgnoreCaseTemplate(a), Self::EqualIgnoreCaseTemplate(b)) => a == b,
+      (Self::MatchTemplate(a), Self::MatchTemplate(b)) => a == b,
       _ => false
     }
   }
 }
 
+// Severity (set with `@severity("high")`), weighting a rule's contribution to compliance scores
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  Low,
+  #[default]
+  Medium,
+  High,
+  Critical,
+}
+
+impl Severity {
+  pub fn weight(&self) -> u32 {
+    match self {
+      Severity::Low => 1,
+      Severity::Medium => 3,
+      Severity::High => 6,
+      Severity::Critical => 10,
+    }
+  }
+}
+
+impl TryFrom<&str> for Severity {
+  type Error = &'static str;
+
+  fn try_from(value: &str) -> Result<Self, Self::Error> {
+    match value.to_ascii_lowercase().as_str() {
+      "low" => Ok(Severity::Low),
+      "medium" => Ok(Severity::Medium),
+      "high" => Ok(Severity::High),
+      "critical" => Ok(Severity::Critical),
+      _ => Err("Unknown severity (expected low, medium, high or critical)"),
+    }
+  }
+}
+
+impl fmt::Display for Severity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Severity::Low => write!(f, "low"),
+      Severity::Medium => write!(f, "medium"),
+      Severity::High => write!(f, "high"),
+      Severity::Critical => write!(f, "critical"),
+    }
+  }
+}
+
+// Metadata (set with `@key("value")` annotations before a rule)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+  pub id: Option<String>,
+  pub severity: Severity,
+  pub remediation: Option<String>, // free-text guidance on fixing a violation
+  pub fixable: bool,               // `--fix` may apply this rule's generated change
+}
+
+impl Metadata {
+  pub fn annotate(mut self, key: &str, value: Option<&str>) -> Result<Self, &'static str> {
+    match (key, value) {
+      ("id", Some(id)) => self.id = Some(id.to_owned()),
+      ("id", None) => return Err("@id requires a value"),
+      ("severity", Some(severity)) => self.severity = Severity::try_from(severity)?,
+      ("severity", None) => return Err("@severity requires a value"),
+      ("remediation", Some(remediation)) => self.remediation = Some(remediation.to_owned()),
+      ("remediation", None) => return Err("@remediation requires a value"),
+      ("fixable", None) => self.fixable = true,
+      ("fixable", Some(_)) => return Err("@fixable doesn't take a value"),
+      _ => return Err("Unknown annotation"),
+    }
+
+    Ok(self)
+  }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Rule {
   pub selector: Selector,
-  pub property: Property,
+  pub expression: Expression,
   pub condition: Condition,
+  pub metadata: Metadata,
+}
+
+impl Rule {
+  // The `@id` annotation if given, otherwise derived from the selector, expression and
+  // condition, e.g. `azure.*:location = "uksouth"`
+  pub fn id(&self) -> String {
+    match &self.metadata.id {
+      Some(id) => id.to_owned(),
+      None => format!("{}:{}", self.selector, self),
+    }
+  }
+}
+
+// Conditions display in rule file syntax, e.g. `= "uksouth"` or `older_than 90d`
+impl fmt::Display for Condition {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Condition::Equal(x) => write!(f, "= {:?}", x),
+      Condition::EqualBool(x) => write!(f, "= {}", x),
+      Condition::EqualIgnoreCase(x) => write!(f, "=i {:?}", x),
+      Condition::Match(x) => write!(f, "~= /{}/", x),
+      Condition::LessThan(x) => write!(f, "< {}", x),
+      Condition::LessOrEqual(x) => write!(f, "<= {}", x),
+      Condition::GreaterThan(x) => write!(f, "> {}", x),
+      Condition::GreaterOrEqual(x) => write!(f, ">= {}", x),
+      Condition::VersionLessThan(x) => write!(f, "< \"{}\"", x),
+      Condition::VersionLessOrEqual(x) => write!(f, "<= \"{}\"", x),
+      Condition::VersionGreaterThan(x) => write!(f, "> \"{}\"", x),
+      Condition::VersionGreaterOrEqual(x) => write!(f, ">= \"{}\"", x),
+      Condition::Within(x) => write!(f, "within \"{}\"", x),
+      Condition::Overlaps(x) => write!(f, "overlaps \"{}\"", x),
+      Condition::EqualTemplate(x) => write!(f, "= \"{}\"", x),
+      Condition::EqualIgnoreCaseTemplate(x) => write!(f, "=i \"{}\"", x),
+      Condition::MatchTemplate(x) => write!(f, "~= /{}/", x),
+      Condition::Before(x) => write!(f, "before {}", x.to_rfc3339()),
+      Condition::OlderThan(x) => write!(f, "older_than {}", describe_duration(x)),
+      Condition::WithinDuration(x) => write!(f, "within {}", describe_duration(x)),
+      Condition::Not(x) => write!(f, "not {}", x),
+      Condition::Element(clauses) => {
+        let clauses: Vec<String> = clauses.iter().map(|(expression, condition)| format!("{} {}", expression, condition)).collect();
+
+        write!(f, "{{ {} }}", clauses.join(" and "))
+      }
+    }
+  }
 }
 
 impl fmt::Display for Rule {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    let (op, expected) = match &self.condition {
-      Condition::Equal(x) => ("equal", x.to_owned()),
-      Condition::Match(x) => ("match", format!("/{}/", x)),
+    write!(f, "{} {}", self.expression, self.condition)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_function_apply() {
+    assert_eq!(Function::Length.apply(&json!("storageacct")), json!(11));
+    assert_eq!(Function::Length.apply(&json!({ "a": 1, "b": 2 })), json!(2));
+    assert_eq!(Function::Length.apply(&Value::Null), Value::Null);
+    assert_eq!(Function::Count.apply(&json!([1, null, 3])), json!(2));
+    assert_eq!(Function::Count.apply(&Value::Null), json!(0));
+
+    assert_eq!(Function::Lower.apply(&json!("UKSouth")), json!("uksouth"));
+    assert_eq!(Function::Trim.apply(&json!(" prod ")), json!("prod"));
+    assert_eq!(Function::NormalizeLocation.apply(&json!("UK South")), json!("uksouth"));
+    assert_eq!(Function::Upper.apply(&json!(["a", "b"])), json!(["A", "B"]));
+  }
+
+  #[test]
+  fn test_version() {
+    assert_eq!(Version::try_from("1.27.3"), Ok(Version(vec![1, 27, 3])));
+    assert_eq!(Version::try_from("TLS1_2"), Ok(Version(vec![1, 2])));
+    assert_eq!(Version::try_from("NODE|14-lts"), Ok(Version(vec![14])));
+    assert!(Version::try_from("latest").is_err());
+
+    let minimum = Condition::VersionGreaterOrEqual(Version(vec![1, 2]));
+    assert!(minimum.is_compliant(&json!("1.2")));
+    assert!(minimum.is_compliant(&json!("1.10")));
+    assert!(minimum.is_compliant(&json!("TLS1_2")));
+    assert!(!minimum.is_compliant(&json!("1.0")));
+    assert!(!minimum.is_compliant(&Function::Version.apply(&json!(1.1))));
+
+    let maximum = Condition::VersionLessThan(Version(vec![1, 28]));
+    assert!(maximum.is_compliant(&json!("1.27.3")));
+    assert!(!maximum.is_compliant(&json!("1.28.0")));
+  }
+
+  #[test]
+  fn test_network_conditions() {
+    let hub = Network::try_from("10.0.0.0/8").unwrap();
+
+    assert!(Condition::Within(hub).is_compliant(&json!("10.20.0.0/16")));
+    assert!(!Condition::Within(hub).is_compliant(&json!("0.0.0.0/0")));
+    assert!(!Condition::Within(hub).is_compliant(&json!("Internet")));
+    assert!(Condition::Overlaps(hub).is_compliant(&json!("0.0.0.0/0")));
+
+    assert_eq!(Function::IsPublicIp.apply(&json!("51.140.1.1")), json!(true));
+    assert_eq!(Function::IsPublicIp.apply(&json!(["10.0.0.4", "*", "Internet"])), json!([false, true, true]));
+    assert_eq!(Function::IsPublicIp.apply(&json!("VirtualNetwork")), json!(false));
+    assert_eq!(Function::IsPublicIp.apply(&json!("Storage")), Value::Null);
+    assert!(Condition::EqualBool(false).is_compliant(&Function::IsPublicIp.apply(&json!("10.0.0.4"))));
+  }
+
+  #[test]
+  fn test_condition_resolve() {
+    let resource = json!({ "tags": { "app": "pay.api", "region": "uksouth" } });
+    let lookup = |property: &Property| match property {
+      Property::Custom(path) => resource.pointer(&format!("/{}", path.replace('.', "/"))).cloned().unwrap_or(Value::Null),
+      _ => Value::Null,
     };
 
-    write!(f, "Expected {:?} to {} {}", self.property, op, expected)
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    let condition = Condition::MatchTemplate(Template::try_from("^${tags.app}-").unwrap());
+    let resolved = condition.resolve(&lookup, now).unwrap();
+    assert!(resolved.is_compliant(&json!("pay.api-web")));
+    assert!(!resolved.is_compliant(&json!("payxapi-web")));
+
+    let condition = Condition::EqualTemplate(Property::Custom("tags.region".to_owned()).into());
+    assert_eq!(condition.resolve(&lookup, now), Some(Condition::Equal("uksouth".to_owned())));
+
+    let condition = Condition::EqualTemplate(Template::try_from("${tags.missing}").unwrap());
+    assert_eq!(condition.resolve(&lookup, now), None);
+  }
+
+  #[test]
+  fn test_date_conditions() {
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let lookup = |_: &Property| Value::Null;
+
+    let old = Condition::OlderThan(Duration::days(90)).resolve(&lookup, now).unwrap();
+    assert!(old.is_compliant(&json!("2026-06-01T09:30:00.0000000Z")));
+    assert!(!old.is_compliant(&json!("2026-10-01T09:30:00+01:00")));
+    assert!(!old.is_compliant(&json!("not a date")));
+    assert!(old.accepts(&json!("not a date")));
+
+    let soon = Condition::WithinDuration(Duration::days(30)).resolve(&lookup, now).unwrap();
+    assert!(soon.is_compliant(&json!("2026-11-01")));
+    assert!(!soon.is_compliant(&json!("2027-01-01")));
+
+    let not_soon = Condition::Not(Box::new(Condition::WithinDuration(Duration::days(30))));
+    assert!(not_soon.resolve(&lookup, now).unwrap().is_compliant(&json!("2027-01-01")));
+
+    // A certificate that expired 60 days ago is due too
+    assert!(soon.is_compliant(&json!("2026-08-19")));
+    assert!(!not_soon.resolve(&lookup, now).unwrap().is_compliant(&json!("2026-08-19")));
+
+    // Negation only flips a real comparison
+    assert!(!not_soon.resolve(&lookup, now).unwrap().accepts(&json!("not a date")));
+    assert_eq!(not_soon.value_kind(), "date");
+    assert_eq!(not_soon.to_string(), "not within 30d");
+  }
+
+  #[test]
+  fn test_condition_is_compliant() {
+    assert!(Condition::EqualIgnoreCase("uksouth".to_owned()).is_compliant(&json!("UKSouth")));
+    assert!(!Condition::Equal("uksouth".to_owned()).is_compliant(&json!("UKSouth")));
+    assert!(Condition::LessOrEqual(24.0).is_compliant(&json!(24)));
+    assert!(!Condition::LessOrEqual(24.0).is_compliant(&json!("24")));
   }
 }
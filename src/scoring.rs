@@ -0,0 +1,125 @@
+use crate::engine::{ Outcome, ResourceCompliance };
+use std::collections::BTreeMap;
+
+// Evaluation counts for a slice of the results, with compliant and non-compliant
+// evaluations also totalled by the weight of each rule's severity
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Score {
+  pub compliant: usize,
+  pub noncompliant: usize,
+  pub waived: usize,
+  pub baselined: usize,
+  pub errors: usize,
+
+  compliant_weight: u32,
+  noncompliant_weight: u32,
+}
+
+impl Score {
+  fn record(&mut self, outcome: &Outcome, weight: u32) {
+    match outcome {
+      Outcome::Compliant => {
+        self.compliant += 1;
+        self.compliant_weight += weight;
+      }
+      Outcome::NonCompliant { .. } => {
+        self.noncompliant += 1;
+        self.noncompliant_weight += weight;
+      }
+      // Known violations still count against the score, they just aren't reported as new
+      Outcome::Baselined { .. } => {
+        self.baselined += 1;
+        self.noncompliant_weight += weight;
+      }
+      Outcome::Waived { .. } => self.waived += 1,
+      Outcome::Error { .. } => self.errors += 1,
+      Outcome::NotApplicable => {}
+    }
+  }
+
+  // The severity-weighted percentage of compliant evaluations, ignoring waived and errored ones
+  pub fn percentage(&self) -> f64 {
+    let scored = self.compliant_weight + self.noncompliant_weight;
+
+    if scored == 0 { 100.0 } else { f64::from(self.compliant_weight) / f64::from(scored) * 100.0 }
+  }
+}
+
+// Scores for all results, and broken down by subscription, resource group, resource kind and rule id
+#[derive(Debug, Default)]
+pub struct Breakdown {
+  pub overall: Score,
+  pub subscriptions: BTreeMap<String, Score>,
+  pub groups: BTreeMap<String, Score>,
+  pub kinds: BTreeMap<String, Score>,
+  pub rules: BTreeMap<String, Score>,
+}
+
+impl Breakdown {
+  pub fn from_results(results: &[ResourceCompliance]) -> Self {
+    let mut breakdown = Breakdown::default();
+
+    for resource in results {
+      for evaluation in &resource.evaluations {
+        let (outcome, weight) = (&evaluation.outcome, evaluation.rule.metadata.severity.weight());
+
+        breakdown.overall.record(outcome, weight);
+        breakdown.subscriptions.entry(resource.subscription_id.clone()).or_default().record(outcome, weight);
+        breakdown.groups.entry(resource.resource_group.clone()).or_default().record(outcome, weight);
+        breakdown.kinds.entry(resource.resource_type.clone()).or_default().record(outcome, weight);
+
+        // Rules that never applied are left out rather than listed as trivially compliant
+        if *outcome != Outcome::NotApplicable {
+          breakdown.rules.entry(evaluation.rule.id()).or_default().record(outcome, weight);
+        }
+      }
+    }
+
+    breakdown
+  }
+
+  // Each breakdown with a heading, in display order
+  pub fn sections(&self) -> Vec<(&'static str, &BTreeMap<String, Score>)> {
+    vec![
+      ("subscription", &self.subscriptions),
+      ("resource group", &self.groups),
+      ("resource kind", &self.kinds),
+      ("rule", &self.rules),
+    ]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::azurerm::Resource;
+  use crate::engine::Engine;
+  use crate::parser;
+  use chrono::{ TimeZone, Utc };
+  use serde_json::json;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn test_weighted_breakdown() {
+    let resources: Vec<Resource> = vec![
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/web-rg/providers/Microsoft.Web/sites/app", "location": "uksouth" }),
+      json!({ "id": "/subscriptions/sub-a/resourceGroups/data-rg/providers/Microsoft.Storage/storageAccounts/store", "location": "uksouth" }),
+    ].into_iter().map(|r| Resource::try_from(r).unwrap()).collect();
+    let source = "azure.* {\n  @id(\"location\") @severity(\"critical\")\n  location = \"uksouth\"\n  @id(\"name\") @severity(\"low\")\n  name ~= /^st/\n}\n";
+    let rules = parser::parse_source(source).unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+
+    let breakdown = Breakdown::from_results(&Engine::new().at(now).evaluate(&resources, &rules));
+
+    // A single low severity failure barely dents the score: (10 + 1 + 10) / (10 + 1 + 10 + 1)
+    assert_eq!((breakdown.overall.compliant, breakdown.overall.noncompliant), (3, 1));
+    assert_eq!(format!("{:.1}", breakdown.overall.percentage()), "95.5");
+
+    assert_eq!(breakdown.subscriptions["sub-a"], breakdown.overall);
+    assert_eq!(format!("{:.1}", breakdown.groups["web-rg"].percentage()), "90.9");
+    assert_eq!(breakdown.groups["data-rg"].percentage(), 100.0);
+    assert_eq!(breakdown.kinds["app_service"].noncompliant, 1);
+    assert_eq!(breakdown.rules["location"].percentage(), 100.0);
+    assert_eq!(breakdown.rules["name"].percentage(), 50.0);
+  }
+}
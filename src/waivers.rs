@@ -0,0 +1,146 @@
+use crate::azurerm::{ Id, Resource };
+use crate::rules::{ Rule, Selector };
+use chrono::NaiveDate;
+use serde_json::Value;
+use std::convert::{ TryFrom, TryInto };
+use std::fmt;
+use std::path::Path;
+
+// [
+//   {
+//     "rule": "app-location",
+//     "resource": "/subscriptions/.../resourceGroups/legacy-rg/providers/Microsoft.Web/sites/legacy-app",
+//     "justification": "Must stay in westeurope until the Q3 migration",
+//     "expires": "2026-09-30"
+//   }
+// ]
+//
+// `resource` is either an ARM resource id or a selector (e.g. "azure.legacy-rg.*.*").
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+  Resource(Id),
+  Selector(Selector),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waiver {
+  pub rule: String,
+  pub target: Target,
+  pub justification: String,
+  pub expires: NaiveDate,
+}
+
+impl Waiver {
+  pub fn applies(&self, rule: &Rule, resource: &Resource) -> bool {
+    self.rule == rule.id() && match &self.target {
+      Target::Resource(id) => id.is_same(resource.id()),
+      Target::Selector(selector) => resource.selector_applies(selector),
+    }
+  }
+
+  // A waiver is valid up to and including its expiry date
+  pub fn is_expired(&self, today: NaiveDate) -> bool {
+    today > self.expires
+  }
+}
+
+impl TryFrom<&Value> for Waiver {
+  type Error = WaiverError;
+
+  fn try_from(value: &Value) -> Result<Self, Self::Error> {
+    let field = |key: &str| {
+      value[key].as_str().ok_or_else(|| WaiverError::Invalid(format!("Waiver is missing `{}`", key)))
+    };
+
+    let resource = field("resource")?;
+    let target = if resource.starts_with('/') {
+      Target::Resource(Id::try_from(resource).map_err(|e| WaiverError::Invalid(e.to_owned()))?)
+    } else {
+      Target::Selector(resource.try_into().map_err(|e: &str| WaiverError::Invalid(e.to_owned()))?)
+    };
+
+    let expires = field("expires")?;
+    let expires = NaiveDate::parse_from_str(expires, "%Y-%m-%d")
+      .map_err(|_| WaiverError::Invalid(format!("Invalid expiry date `{}` (expected YYYY-MM-DD)", expires)))?;
+
+    Ok(Waiver {
+      rule: field("rule")?.to_owned(),
+      target,
+      justification: field("justification")?.to_owned(),
+      expires,
+    })
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WaiverError {
+  Io(String),
+  Invalid(String),
+}
+
+impl fmt::Display for WaiverError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(reason) => write!(f, "Failed to read waivers: {}", reason),
+      Self::Invalid(reason) => write!(f, "Invalid waivers: {}", reason),
+    }
+  }
+}
+
+pub fn parse_waivers(path: impl AsRef<Path>) -> Result<Vec<Waiver>, WaiverError> {
+  let contents = std::fs::read_to_string(path).map_err(|e| WaiverError::Io(e.to_string()))?;
+  let json: Value = serde_json::from_str(&contents).map_err(|e| WaiverError::Invalid(e.to_string()))?;
+
+  json.as_array()
+    .ok_or_else(|| WaiverError::Invalid("Expected a list of waivers".to_owned()))?
+    .iter()
+    .map(Waiver::try_from)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::parse_source;
+  use serde_json::json;
+
+  #[test]
+  fn test_waiver_applies() {
+    let resource = Resource::try_from(json!({
+      "id": "/subscriptions/00d88f1a/resourceGroups/legacy-rg/providers/Microsoft.Web/sites/legacy-app",
+      "location": "westeurope",
+    })).unwrap();
+    let rules = parse_source("azure.* {\n  @id(\"app-location\")\n  location = \"uksouth\"\n}\n").unwrap();
+
+    let waiver = Waiver::try_from(&json!({
+      "rule": "app-location",
+      "resource": "/subscriptions/00d88f1a/resourcegroups/legacy-rg/providers/Microsoft.Web/sites/legacy-app",
+      "justification": "Migrating in Q3",
+      "expires": "2026-09-30",
+    })).unwrap();
+    assert!(waiver.applies(&rules[0], &resource));
+    assert!(!waiver.is_expired(NaiveDate::from_ymd_opt(2026, 9, 30).unwrap()));
+    assert!(waiver.is_expired(NaiveDate::from_ymd_opt(2026, 10, 1).unwrap()));
+
+    let waiver = Waiver::try_from(&json!({
+      "rule": "app-location",
+      "resource": "azure.other-rg",
+      "justification": "Migrating in Q3",
+      "expires": "2026-09-30",
+    })).unwrap();
+    assert!(!waiver.applies(&rules[0], &resource));
+  }
+
+  #[test]
+  fn test_invalid_waiver() {
+    let waiver = Waiver::try_from(&json!({
+      "rule": "app-location",
+      "resource": "azure.legacy-rg",
+      "justification": "Migrating in Q3",
+      "expires": "Q3",
+    }));
+
+    assert_eq!(waiver, Err(WaiverError::Invalid("Invalid expiry date `Q3` (expected YYYY-MM-DD)".to_owned())));
+  }
+}